@@ -0,0 +1,161 @@
+use std::{str::FromStr, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_turborand::RngComponent;
+
+mod systems;
+use systems::*;
+
+use crate::{
+    config::Headless,
+    enter_stage::SkipStageIntro,
+    game::prelude::{GameMode, GameTick, InputSet, InputSource, RunModifiers},
+    GameState,
+};
+
+// Seconds on the main menu before the demo starts by itself
+pub const ATTRACT_SECS: u64 = 20;
+
+// Plays the game through `PlayerInput`, as the headless policy and as the
+// attract mode demo of the main menu
+pub struct AutopilotPlugin;
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            GameTick,
+            autopilot.in_set(InputSet).run_if(
+                in_state(GameState::InGame).and_then(resource_equals(InputSource::Autopilot)),
+            ),
+        )
+        .add_systems(OnEnter(GameState::MainMenu), reset_attract_timer)
+        .add_systems(
+            Update,
+            (
+                start_demo.run_if(in_state(GameState::MainMenu)),
+                end_demo.run_if(
+                    resource_exists::<Demo>().and_then(
+                        in_state(GameState::InGame).or_else(in_state(GameState::GameOver)),
+                    ),
+                ),
+                demo_ui.run_if(
+                    resource_exists::<Demo>()
+                        .and_then(in_state(GameState::InGame))
+                        .and_then(not(resource_exists::<Headless>())),
+                ),
+            ),
+        );
+    }
+}
+
+/**
+ * How well the autopilot plays
+ */
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Skill {
+    Rookie,
+    #[default]
+    Veteran,
+    Ace,
+}
+
+impl Skill {
+    // seconds an enemy has to be on screen before it is shot at
+    pub fn reaction_secs(self) -> f32 {
+        match self {
+            Skill::Rookie => 0.8,
+            Skill::Veteran => 0.4,
+            Skill::Ace => 0.15,
+        }
+    }
+
+    // how far off the intercept point a shot may land
+    pub fn aim_error(self) -> f32 {
+        match self {
+            Skill::Rookie => 14.0,
+            Skill::Veteran => 6.0,
+            Skill::Ace => 0.0,
+        }
+    }
+
+    pub fn shot_interval_secs(self) -> f32 {
+        match self {
+            Skill::Rookie => 0.6,
+            Skill::Veteran => 0.35,
+            Skill::Ace => 0.2,
+        }
+    }
+
+    // below this much ammo only missiles headed for a city or the tank are shot down
+    pub fn ammo_reserve(self) -> u8 {
+        match self {
+            Skill::Rookie => 0,
+            Skill::Veteran => 8,
+            Skill::Ace => 12,
+        }
+    }
+}
+
+impl FromStr for Skill {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "rookie" => Ok(Skill::Rookie),
+            "veteran" => Ok(Skill::Veteran),
+            "ace" => Ok(Skill::Ace),
+            _ => Err(format!(
+                "unknown skill '{name}', expected rookie, veteran or ace"
+            )),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Autopilot {
+    pub skill: Skill,
+    // aim errors are drawn from their own rng, so the enemies of a run stay
+    // the same whoever plays it
+    rng: RngComponent,
+    // when each enemy was first seen
+    seen: HashMap<Entity, f32>,
+    last_shot: f32,
+}
+
+impl Autopilot {
+    pub fn new(skill: Skill, seed: u64) -> Self {
+        Self {
+            skill,
+            rng: RngComponent::with_seed(seed),
+            seen: HashMap::new(),
+            last_shot: f32::MIN,
+        }
+    }
+}
+
+// An attract mode run played by the autopilot, ended by any key
+#[derive(Resource)]
+pub struct Demo;
+
+impl Demo {
+    // the campaign played from its first stage by a veteran, without modifiers
+    pub fn start(commands: &mut Commands, seed: u64) {
+        commands.insert_resource(Demo);
+        commands.insert_resource(SkipStageIntro);
+        commands.insert_resource(InputSource::Autopilot);
+        commands.insert_resource(Autopilot::new(Skill::Veteran, seed));
+        commands.insert_resource(GameMode::Campaign);
+        commands.insert_resource(RunModifiers::default());
+    }
+}
+
+#[derive(Resource)]
+pub struct AttractTimer(pub Timer);
+
+impl Default for AttractTimer {
+    fn default() -> Self {
+        Self(Timer::new(
+            Duration::from_secs(ATTRACT_SECS),
+            TimerMode::Once,
+        ))
+    }
+}
@@ -0,0 +1,302 @@
+use bevy::{math::vec2, prelude::*};
+use bevy_egui::{
+    egui::{self, Align2, Color32, FontId, RichText},
+    EguiContexts,
+};
+use bevy_turborand::DelegatedRng;
+
+use crate::{
+    campaign::Campaign,
+    enter_stage::SkipStageIntro,
+    game::prelude::{
+        flame_steps, reset_run, shot_flame_scale, ActiveBattery, ActivePowerUps, Battery, City,
+        Destroyed, Enemy, FlameRadius, Flyer, InputSource, Missile, MissileReserve, PlayerInput,
+        RunModifiers, RunSeed, RunUpgrades, Stage, StageHandle, Stepper, TankBody, Wave,
+        FLAME_RADIUS_STEPS, FLAME_STEP_SECS,
+    },
+    replay::Replay,
+    GameState, SCREEN,
+};
+
+use super::{AttractTimer, Autopilot, Demo};
+
+const GROUND_Y: f32 = -SCREEN.y / 2.0 + 16.0;
+// the tank keeps this far from the edges of the screen
+const TANK_MARGIN: f32 = 24.0;
+
+// An enemy as the autopilot sees it
+struct Target {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    // seconds until it hits the ground or leaves the screen
+    remaining: f32,
+    // whether it will take out a city or the tank
+    threat: bool,
+    is_ufo: bool,
+}
+
+fn flame_max(steps: &[i32]) -> f32 {
+    steps.iter().copied().max().unwrap_or_default() as f32
+}
+
+/**
+ * Seconds from a missile arriving until its flames are at their widest. The
+ * first step of the flames is checked one step after the explosion.
+ */
+fn flame_peak_secs(steps: &[i32]) -> f32 {
+    let max = steps.iter().copied().max().unwrap_or_default();
+    let first = steps.iter().position(|r| *r == max).unwrap_or(0);
+    let last = steps.iter().rposition(|r| *r == max).unwrap_or(0);
+
+    ((first + last) as f32 / 2.0 + 1.0) * FLAME_STEP_SECS
+}
+
+/**
+ * Where to aim from `origin` so the flames are at their widest when the
+ * target passes, and the seconds the shot takes to get there
+ */
+fn intercept(origin: Vec2, target: &Target, speed: f32, peak: f32) -> Option<(Vec2, f32)> {
+    let mut flight = target.pos.distance(origin) / speed;
+    // the aim point and the flight time depend on each other, a few rounds
+    // of refining is plenty at these speeds
+    for _ in 0..4 {
+        let aim = target.pos + target.vel * (flight + peak);
+        flight = aim.distance(origin) / speed;
+    }
+    if flight + peak > target.remaining {
+        return None;
+    }
+
+    let aim = target.pos + target.vel * (flight + peak);
+    (aim.y > GROUND_Y && aim.x.abs() < SCREEN.x / 2.0).then_some((aim, flight))
+}
+
+pub fn autopilot(
+    mut input: ResMut<PlayerInput>,
+    mut autopilot: ResMut<Autopilot>,
+    time: Res<Time>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    wave: Res<Wave>,
+    enemy_missiles: Query<(Entity, &Transform, &Missile), With<Enemy>>,
+    flyers: Query<(Entity, &Transform, &Flyer), With<Enemy>>,
+    player_missiles: Query<(&Transform, &Missile), Without<Enemy>>,
+    flames: Query<(&Transform, &Stepper<FlameRadius, i32>)>,
+    cities: Query<&Transform, (With<City>, Without<Destroyed>)>,
+    batteries: Query<(&Transform, &TankBody, &Battery, &MissileReserve)>,
+    active: Res<ActiveBattery>,
+    power_ups: Res<ActivePowerUps>,
+    upgrades: Res<RunUpgrades>,
+    modifiers: Res<RunModifiers>,
+) {
+    let intact: Vec<_> = batteries
+        .iter()
+        .filter(|(_, tank, _, _)| matches!(tank, TankBody::Intact))
+        .collect();
+    // the battery moved by the autopilot
+    let moved = intact
+        .iter()
+        .find(|(_, _, battery, _)| battery.index == active.0)
+        .or(intact.first());
+    let (Some(stage), Some((tank, _, _, _))) = (stages.get(&stage.0), moved) else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let skill = autopilot.skill;
+    let origin = tank.translation.truncate();
+    // where a shot can be fired from right now
+    let ready: Vec<Vec2> = intact
+        .iter()
+        .filter(|(_, _, battery, ammo)| battery.cooldown.finished() && ammo.0 > 0)
+        .map(|(transform, _, _, _)| transform.translation.truncate())
+        .collect();
+    // how close to a city or battery the blast of an enemy missile does harm
+    let reach = flame_max(&FLAME_RADIUS_STEPS) + 16.0;
+    // the flames of a shot, as wide as the game will make them
+    let shot_flames = flame_steps(shot_flame_scale(&power_ups, &upgrades, &modifiers));
+    let shot_reach = flame_max(&shot_flames);
+    let hits = |dest: Vec2| {
+        intact
+            .iter()
+            .any(|(transform, _, _, _)| (dest.x - transform.translation.x).abs() < reach)
+            || cities
+                .iter()
+                .any(|city| (city.translation.x - dest.x).abs() < reach)
+    };
+
+    let mut targets: Vec<Target> = Vec::new();
+    for (entity, transform, missile) in enemy_missiles.iter() {
+        let pos = transform.translation.truncate();
+        targets.push(Target {
+            entity,
+            pos,
+            vel: (missile.dest - pos).normalize_or_zero() * missile.vel,
+            remaining: missile.dest.distance(pos) / missile.vel.max(f32::EPSILON),
+            threat: hits(missile.dest),
+            is_ufo: false,
+        });
+    }
+    for (entity, transform, flyer) in flyers.iter() {
+        let pos = transform.translation.truncate();
+        targets.push(Target {
+            entity,
+            pos,
+            vel: flyer.velocity(pos),
+            remaining: flyer.remaining_secs(pos),
+            threat: false,
+            is_ufo: true,
+        });
+    }
+
+    autopilot
+        .seen
+        .retain(|entity, _| targets.iter().any(|target| target.entity == *entity));
+    for target in targets.iter() {
+        autopilot.seen.entry(target.entity).or_insert(now);
+    }
+
+    // the most urgent threats first, then whatever is worth points
+    targets.sort_by(|a, b| {
+        b.threat
+            .cmp(&a.threat)
+            .then(a.is_ufo.cmp(&b.is_ufo))
+            .then(a.remaining.total_cmp(&b.remaining))
+    });
+
+    let ammo: usize = intact.iter().map(|(_, _, _, ammo)| ammo.0 as usize).sum();
+    let conserving = ammo <= skill.ammo_reserve() as usize;
+    let peak = flame_peak_secs(&shot_flames);
+    // a target is covered when a shot on its way or a burning explosion will get it
+    let covered = |target: &Target| {
+        let by_shots = player_missiles.iter().any(|(transform, missile)| {
+            let flight = missile.dest.distance(transform.translation.truncate()) / missile.vel;
+            let pos = target.pos + target.vel * (flight + peak);
+            pos.distance(missile.dest) < shot_reach
+        });
+        let by_flames = flames.iter().any(|(transform, stepper)| {
+            !stepper.is_finished()
+                && transform.translation.truncate().distance(target.pos) < shot_reach
+        });
+        by_shots || by_flames
+    };
+
+    let choice = targets
+        .iter()
+        .filter(|target| target.threat || !conserving)
+        .filter(|target| {
+            autopilot
+                .seen
+                .get(&target.entity)
+                .map_or(false, |seen| now - seen >= skill.reaction_secs())
+        })
+        .filter(|target| !covered(target))
+        .find_map(|target| {
+            // the game fires from the battery closest to the sight
+            let from = ready
+                .iter()
+                .min_by(|a, b| a.distance(target.pos).total_cmp(&b.distance(target.pos)))?;
+            intercept(
+                *from,
+                target,
+                upgrades.missile_speed() * power_ups.missile_scale(),
+                peak,
+            )
+        });
+
+    input.fire = false;
+    if let Some((aim, _)) = choice {
+        let error = skill.aim_error();
+        let offset = vec2(
+            autopilot.rng.f32() * 2.0 - 1.0,
+            autopilot.rng.f32() * 2.0 - 1.0,
+        ) * error;
+        input.cursor = aim + offset;
+        if ammo > 0 && now - autopilot.last_shot >= skill.shot_interval_secs() {
+            input.fire = true;
+            autopilot.last_shot = now;
+        }
+    }
+
+    // step out of the way of missiles headed for the battery, otherwise drift
+    // back to the middle where every city is in reach
+    let incoming = targets
+        .iter()
+        .filter(|target| !target.is_ufo && target.remaining < 2.0)
+        .map(|target| target.pos + target.vel * target.remaining)
+        .find(|dest| (dest.x - origin.x).abs() < reach);
+    let edge = SCREEN.x / 2.0 - TANK_MARGIN;
+    let (left, right) = match incoming {
+        Some(dest) if dest.x >= origin.x => (origin.x > -edge, false),
+        Some(_) => (false, origin.x < edge),
+        None => (origin.x > reach, origin.x < -reach),
+    };
+    input.left = left;
+    input.right = right;
+}
+
+pub fn reset_attract_timer(mut commands: Commands) {
+    commands.insert_resource(AttractTimer::default());
+}
+
+pub fn start_demo(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut attract_timer: ResMut<AttractTimer>,
+    keyboard: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    seed: Res<RunSeed>,
+    replay: Option<Res<Replay>>,
+    mut campaign: ResMut<Campaign>,
+) {
+    // any activity on the menu holds the demo off
+    if keyboard.get_just_pressed().next().is_some() || buttons.get_just_pressed().next().is_some() {
+        attract_timer.0.reset();
+    }
+    attract_timer.0.tick(time.delta());
+
+    if attract_timer.0.just_finished() {
+        if replay.is_some() {
+            Replay::end(&mut commands);
+        }
+        Demo::start(&mut commands, seed.0);
+        campaign.current = 0;
+        next_state.set(GameState::EnterGame);
+    }
+}
+
+pub fn end_demo(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut campaign: ResMut<Campaign>,
+    keyboard: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    state: Res<State<GameState>>,
+) {
+    let interrupted =
+        keyboard.get_just_pressed().next().is_some() || buttons.get_just_pressed().next().is_some();
+    if !interrupted && *state.get() != GameState::GameOver {
+        return;
+    }
+
+    commands.remove_resource::<Demo>();
+    commands.remove_resource::<SkipStageIntro>();
+    commands.remove_resource::<Autopilot>();
+    commands.insert_resource(InputSource::Devices);
+    reset_run(&mut commands);
+    campaign.current = 0;
+    next_state.set(GameState::MainMenu);
+}
+
+pub fn demo_ui(mut contexts: EguiContexts) {
+    egui::Area::new("demo")
+        .anchor(Align2::CENTER_BOTTOM, egui::emath::vec2(0., -40.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                RichText::new("DEMO - press any key")
+                    .font(FontId::proportional(18.))
+                    .color(Color32::WHITE),
+            );
+        });
+}
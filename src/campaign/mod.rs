@@ -0,0 +1,47 @@
+use bevy::{asset::LoadState, prelude::*};
+
+mod systems;
+use systems::*;
+
+use crate::{
+    game::prelude::{GameMode, GameTick, Stage, TickSet},
+    GameState,
+};
+
+// Number of waves a stage lasts when the stage file doesn't say otherwise
+pub const DEFAULT_STAGE_WAVES: usize = 5;
+
+// Discovers the stage files and moves the player from one stage to the
+// next as waves are cleared
+pub struct CampaignPlugin;
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_campaign).add_systems(
+            GameTick,
+            advance_stage
+                .in_set(TickSet::Play)
+                .run_if(in_state(GameState::InGame).and_then(resource_equals(GameMode::Campaign))),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct Campaign {
+    pub stages: Vec<Handle<Stage>>,
+    pub current: usize,
+}
+
+impl Campaign {
+    pub fn stage(&self) -> Handle<Stage> {
+        self.stages[self.current].clone()
+    }
+
+    /**
+     * The index of the next stage that is still playable. Stages that failed
+     * to load are skipped.
+     */
+    pub fn next(&self, asset_server: &AssetServer) -> Option<usize> {
+        (self.current + 1..self.stages.len())
+            .find(|i| asset_server.get_load_state(&self.stages[*i]) != LoadState::Failed)
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::{
+    game::prelude::{Score, Stage, StageHandle, Wave, WaveCompletedEvent},
+    shop::ShopOpen,
+    GameState,
+};
+
+use super::{Campaign, DEFAULT_STAGE_WAVES};
+
+const STAGE_FOLDER: &str = "stages";
+const FIRST_STAGE: &str = "stages/1.stage.json";
+
+pub fn load_campaign(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    campaign: Option<Res<Campaign>>,
+) {
+    // a campaign of hand picked stages was set up before startup
+    if campaign.is_some() {
+        return;
+    }
+
+    let mut stages: Vec<(String, Handle<Stage>)> = match asset_server.load_folder(STAGE_FOLDER) {
+        Ok(handles) => handles
+            .into_iter()
+            .filter_map(|handle| {
+                let path = asset_server
+                    .get_handle_path(&handle)?
+                    .path()
+                    .to_string_lossy()
+                    .to_string();
+                path.ends_with(".stage.json")
+                    .then(|| (path, handle.typed::<Stage>()))
+            })
+            .collect(),
+        Err(err) => {
+            // folders can't be listed on every platform (wasm), fall back to the first stage
+            warn!("Could not discover stages in '{STAGE_FOLDER}': {err:?}");
+            Vec::new()
+        }
+    };
+
+    if stages.is_empty() {
+        stages.push((FIRST_STAGE.to_string(), asset_server.load(FIRST_STAGE)));
+    }
+
+    stages.sort_by_cached_key(|(path, _)| stage_order(path));
+    info!(
+        "Campaign stages: {:?}",
+        stages.iter().map(|(path, _)| path).collect::<Vec<_>>()
+    );
+
+    commands.insert_resource(Campaign {
+        stages: stages.into_iter().map(|(_, handle)| handle).collect(),
+        current: 0,
+    });
+}
+
+/**
+ * Stage files are ordered by the number they start with, "2.stage.json"
+ * comes before "10.stage.json". Unnumbered files are played last.
+ */
+fn stage_order(path: &str) -> (usize, String) {
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let number: String = name.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    (number.parse().unwrap_or(usize::MAX), name)
+}
+
+fn is_stage_cleared(stage: &Stage, waves: usize, score: usize) -> bool {
+    match (stage.waves, stage.goal_score) {
+        (None, None) => waves >= DEFAULT_STAGE_WAVES,
+        (max_waves, goal) => {
+            max_waves.map_or(false, |max| waves >= max) || goal.map_or(false, |goal| score >= goal)
+        }
+    }
+}
+
+pub fn advance_stage(
+    mut wave_completed: EventReader<WaveCompletedEvent>,
+    mut campaign: ResMut<Campaign>,
+    mut next_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    wave: Res<Wave>,
+    score: Res<Score>,
+    shop: ShopOpen,
+) {
+    for WaveCompletedEvent { wave: finished, .. } in wave_completed.iter() {
+        let Some(stage) = stages.get(&stage.0) else {
+            continue;
+        };
+
+        let stage_waves = finished + 1 - wave.first;
+        if !is_stage_cleared(stage, stage_waves, score.0) {
+            continue;
+        }
+
+        // the last stage keeps going until the player is overrun
+        if let Some(next) = campaign.next(&asset_server) {
+            campaign.current = next;
+            next_state.set(if shop.is_open() {
+                GameState::Shop
+            } else {
+                GameState::EnterGame
+            });
+        }
+    }
+}
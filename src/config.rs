@@ -2,9 +2,22 @@ use bevy::prelude::Resource;
 
 #[derive(Resource)]
 pub struct Debug(pub bool);
+// Present when the game runs without a window, see `headless`
+#[derive(Resource)]
+pub struct Headless;
 #[derive(Default)]
 pub struct ProgramConfig {
     pub debug: bool,
+    pub seed: Option<u64>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub headless: bool,
+    pub stage: Option<String>,
+    pub policy: Option<String>,
+    pub skill: Option<String>,
+    pub waves: Option<usize>,
+    // a daily challenge share code to play back, or a file holding one
+    pub verify: Option<String>,
 }
 
 impl ProgramConfig {
@@ -14,15 +27,74 @@ impl ProgramConfig {
             return Ok(cfg);
         }
 
-        for arg in args {
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
             match arg.as_ref() {
                 "-d" | "--debug" => {
                     cfg.debug = true;
                 }
+                "-s" | "--seed" => {
+                    let seed = args.next().ok_or("--seed needs a number")?;
+                    cfg.seed = Some(seed.parse().map_err(|_| "--seed must be a whole number")?);
+                }
+                "--record" => {
+                    cfg.record = Some(args.next().ok_or("--record needs a file")?.clone());
+                }
+                "--replay" => {
+                    cfg.replay = Some(args.next().ok_or("--replay needs a file")?.clone());
+                }
+                "--headless" => {
+                    cfg.headless = true;
+                }
+                "--stage" => {
+                    cfg.stage = Some(args.next().ok_or("--stage needs a file")?.clone());
+                }
+                "--policy" => {
+                    cfg.policy = Some(args.next().ok_or("--policy needs a name")?.clone());
+                }
+                "--skill" => {
+                    cfg.skill = Some(args.next().ok_or("--skill needs a name")?.clone());
+                }
+                "--waves" => {
+                    let waves = args.next().ok_or("--waves needs a number")?;
+                    cfg.waves = Some(
+                        waves
+                            .parse()
+                            .map_err(|_| "--waves must be a whole number")?,
+                    );
+                }
+                "--verify" => {
+                    cfg.verify = Some(
+                        args.next()
+                            .ok_or("--verify needs a code or a file")?
+                            .clone(),
+                    );
+                    cfg.headless = true;
+                }
                 _ => return Err("unknown argument"),
             }
         }
 
+        if cfg.record.is_some() && cfg.replay.is_some() {
+            return Err("can't record while replaying");
+        }
+        let simulated = cfg.stage.is_some()
+            || cfg.policy.is_some()
+            || cfg.skill.is_some()
+            || cfg.waves.is_some();
+        if !cfg.headless && simulated {
+            return Err("--stage, --policy, --skill and --waves need --headless");
+        }
+        if cfg.policy.is_some() && cfg.replay.is_some() {
+            return Err("a replay already decides every input, drop --policy");
+        }
+
+        let decided =
+            cfg.seed.is_some() || cfg.record.is_some() || cfg.replay.is_some() || simulated;
+        if cfg.verify.is_some() && decided {
+            return Err("a share code decides the whole run, --verify takes no other options");
+        }
+
         Ok(cfg)
     }
 }
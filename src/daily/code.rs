@@ -0,0 +1,178 @@
+use std::str::FromStr;
+
+use crate::{
+    game::prelude::PlayerInput,
+    replay::{decode, encode},
+};
+
+use super::DailyChallenge;
+
+const CODE_PREFIX: &str = "INV1";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/**
+ * The result of a daily challenge with every input that led to it, so
+ * another install can play it back and see the same score:
+ * `INV1.<day>.<score>.<wave>.<replay>` with the replay in URL safe base64
+ */
+pub struct ShareCode {
+    pub day: i64,
+    pub score: usize,
+    // the wave the run ended on, counted from 1
+    pub wave: usize,
+    pub ticks: Vec<PlayerInput>,
+}
+
+impl ShareCode {
+    pub fn encode(&self) -> String {
+        let seed = DailyChallenge::for_day(self.day).seed;
+        let replay = encode(seed, &self.ticks);
+        format!(
+            "{CODE_PREFIX}.{}.{}.{}.{}",
+            self.day,
+            self.score,
+            self.wave,
+            to_base64(replay.as_bytes())
+        )
+    }
+
+    pub fn decode(code: &str) -> Result<ShareCode, String> {
+        let fields: Vec<&str> = code.trim().split('.').collect();
+        let &[CODE_PREFIX, day, score, wave, replay] = fields.as_slice() else {
+            return Err("not a daily challenge code".to_string());
+        };
+        let day = number(day, "day")?;
+        let score = number(score, "score")?;
+        let wave = number(wave, "wave")?;
+
+        let replay = String::from_utf8(from_base64(replay)?)
+            .map_err(|_| "the replay of the code is not text".to_string())?;
+        let (seed, ticks) = decode(&replay)?;
+        // the seed follows from the day, a code can't bring its own
+        if seed != DailyChallenge::for_day(day).seed {
+            return Err("the replay of the code was not played on its day".to_string());
+        }
+
+        Ok(ShareCode {
+            day,
+            score,
+            wave,
+            ticks,
+        })
+    }
+}
+
+fn number<T: FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("the {name} of the code is not a number"))
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 4 / 3 + 3);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | (*byte as u32) << (16 - 8 * i)
+        });
+        // every byte of the chunk takes one digit and a bit more, no padding
+        for i in 0..=chunk.len() {
+            text.push(BASE64[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+
+    text
+}
+
+fn from_base64(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text
+        .bytes()
+        .map(|digit| {
+            BASE64
+                .iter()
+                .position(|other| *other == digit)
+                .map(|value| value as u32)
+                .ok_or_else(|| format!("'{}' can't be in a code", digit as char))
+        })
+        .collect::<Result<Vec<u32>, String>>()?;
+    if digits.len() % 4 == 1 {
+        return Err("the code is cut off".to_string());
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, digit)| bits | digit << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use super::*;
+
+    fn code() -> ShareCode {
+        let tick = |x, fire, battery| PlayerInput {
+            cursor: Vec2::new(x, -12.5),
+            fire,
+            battery,
+            left: false,
+            right: true,
+        };
+        ShareCode {
+            day: 19_782,
+            score: 12_345,
+            wave: 7,
+            ticks: vec![
+                tick(0.0, false, None),
+                tick(0.0, false, None),
+                tick(31.25, true, Some(2)),
+                tick(-100.0, false, None),
+            ],
+        }
+    }
+
+    #[test]
+    fn code_survives_the_round_trip() {
+        let shared = code();
+        let decoded = ShareCode::decode(&shared.encode()).unwrap();
+
+        assert_eq!(decoded.day, shared.day);
+        assert_eq!(decoded.score, shared.score);
+        assert_eq!(decoded.wave, shared.wave);
+        assert_eq!(decoded.ticks, shared.ticks);
+    }
+
+    #[test]
+    fn code_played_on_another_day_is_rejected() {
+        let text = code().encode().replacen(".19782.", ".19783.", 1);
+        assert!(ShareCode::decode(&text).is_err());
+    }
+
+    #[test]
+    fn tampered_code_is_rejected() {
+        let text = code().encode();
+        assert!(ShareCode::decode(&text.replacen("INV1", "INV2", 1)).is_err());
+        assert!(ShareCode::decode(&text.replacen(".12345.", ".lots.", 1)).is_err());
+        assert!(ShareCode::decode(&format!("{text}!")).is_err());
+        assert!(ShareCode::decode(&text[..text.len() - 5]).is_err());
+    }
+
+    #[test]
+    fn base64_round_trips_every_length() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in 0..8 {
+            assert_eq!(
+                from_base64(&to_base64(&bytes[250 - len..250])).unwrap(),
+                &bytes[250 - len..250]
+            );
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+mod code;
+mod systems;
+pub use code::ShareCode;
+use systems::*;
+
+use crate::{
+    config::Headless,
+    data_file, date,
+    game::prelude::{Modifier, Score},
+    GameState,
+};
+
+const DAILY_FILE: &str = "daily.json";
+// mixed into the day, so the seed of a day isn't just its number
+const SEED_SALT: u64 = 0x5eed_da11_c4a1_1e9e;
+// the modifiers a day picks from
+const DAILY_MODIFIERS: [Modifier; 3] = [
+    Modifier::HalfAmmo,
+    Modifier::FastMissiles,
+    Modifier::NoCityRestoration,
+];
+
+// A challenge a day: an endless run everybody plays from the same seed and
+// with the same modifiers, scored on the first attempt only
+pub struct DailyPlugin;
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_daily_log)
+            .add_systems(
+                OnEnter(GameState::EnterGame),
+                setup_daily_run.run_if(resource_exists::<DailyRun>()),
+            )
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                finish_daily_run.run_if(resource_exists::<DailyRun>()),
+            )
+            .add_systems(
+                Update,
+                (copy_share_code, daily_result_ui).run_if(
+                    in_state(GameState::GameOver)
+                        .and_then(resource_exists::<DailyRun>())
+                        .and_then(not(resource_exists::<Headless>())),
+                ),
+            )
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                leave_daily_run.run_if(resource_exists::<DailyRun>()),
+            );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DailyChallenge {
+    // days since 1970-01-01
+    pub day: i64,
+    pub seed: u64,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl DailyChallenge {
+    // none where the clock can't be read
+    pub fn today() -> Option<Self> {
+        date::days_since_epoch().map(Self::for_day)
+    }
+
+    /**
+     * Every install comes up with the same challenge for a day, its modifiers
+     * picked by the bits of its seed
+     */
+    pub fn for_day(day: i64) -> Self {
+        let seed = splitmix64(day as u64 ^ SEED_SALT);
+        // at least one of them, at most all
+        let picked = (seed >> 32) as usize % ((1 << DAILY_MODIFIERS.len()) - 1) + 1;
+        let modifiers = DAILY_MODIFIERS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| picked & (1 << i) != 0)
+            .map(|(_, modifier)| *modifier)
+            .collect();
+
+        Self {
+            day,
+            seed,
+            modifiers,
+        }
+    }
+
+    pub fn date(&self) -> String {
+        date::format_day(self.day)
+    }
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Attempt {
+    // the day's scored attempt, before it has started
+    Scored,
+    // the day's scored attempt, being played and recorded
+    Recording,
+    // the day has been played already, nothing is kept
+    Practice,
+}
+
+// Present while a daily challenge is played
+#[derive(Resource)]
+pub struct DailyRun {
+    pub challenge: DailyChallenge,
+    pub attempt: Attempt,
+    // the code of the run that just ended, if it was the scored one
+    pub code: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DailyEntry {
+    // YYYY-MM-DD
+    pub date: String,
+    // none until the attempt is over, and for good if it was left early
+    pub score: Option<usize>,
+    pub wave: Option<usize>,
+    pub code: Option<String>,
+}
+
+/**
+ * The days played, kept next to the high scores. A day is in here from the
+ * moment its attempt starts.
+ */
+#[derive(Resource, Default)]
+pub struct DailyLog {
+    pub entries: Vec<DailyEntry>,
+    // where the log is saved, none where there is no file system (wasm)
+    path: Option<PathBuf>,
+}
+
+impl DailyLog {
+    pub fn load() -> Self {
+        let path = data_file::path(DAILY_FILE);
+        let entries = data_file::load(path.as_deref(), "daily log");
+
+        Self { entries, path }
+    }
+
+    pub fn save(&self) {
+        data_file::save(self.path.as_deref(), &self.entries, "daily log");
+    }
+
+    pub fn entry(&self, date: &str) -> Option<&DailyEntry> {
+        self.entries.iter().find(|entry| entry.date == date)
+    }
+
+    // where the inputs of a day's attempt are recorded, none without a file system
+    pub fn replay_path(&self, date: &str) -> Option<String> {
+        let dir = self.path.as_ref()?.parent()?;
+        Some(
+            dir.join(format!("daily-{date}.replay"))
+                .to_string_lossy()
+                .to_string(),
+        )
+    }
+
+    /**
+     * Starts the challenge, the scored attempt if the day hasn't been played.
+     * The attempt counts from here, leaving it early doesn't give another.
+     */
+    pub fn start(&mut self, commands: &mut Commands, challenge: DailyChallenge) {
+        let date = challenge.date();
+        let attempt = if self.entry(&date).is_some() {
+            Attempt::Practice
+        } else {
+            self.entries.push(DailyEntry {
+                date,
+                score: None,
+                wave: None,
+                code: None,
+            });
+            self.save();
+            Attempt::Scored
+        };
+        commands.insert_resource(DailyRun {
+            challenge,
+            attempt,
+            code: None,
+        });
+    }
+}
+
+/**
+ * A share code to check, played back headless with the challenge of its day
+ */
+#[derive(Resource)]
+pub struct Verification {
+    pub challenge: DailyChallenge,
+    pub score: usize,
+    pub wave: usize,
+}
+
+impl Verification {
+    // the code itself, or a file holding it
+    pub fn load(code: &str) -> Result<(Verification, ShareCode), String> {
+        let text = match fs::read_to_string(code) {
+            Ok(text) => text,
+            Err(_) => code.to_string(),
+        };
+        let code = ShareCode::decode(&text)?;
+
+        Ok((
+            Verification {
+                challenge: DailyChallenge::for_day(code.day),
+                score: code.score,
+                wave: code.wave,
+            },
+            code,
+        ))
+    }
+
+    pub fn matches(&self, score: &Score, wave: usize) -> bool {
+        self.score == score.0 && self.wave == wave
+    }
+}
@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32, FontId, RichText},
+    EguiContexts,
+};
+
+use crate::{
+    game::prelude::{RunModifiers, RunSeed, Score, Wave},
+    replay::Recording,
+};
+
+use super::{Attempt, DailyLog, DailyRun, ShareCode};
+
+pub fn load_daily_log(mut commands: Commands) {
+    commands.insert_resource(DailyLog::load());
+}
+
+/**
+ * Every run of the challenge, retries too, is played from the seed and with
+ * the modifiers of the day, so the scored one can be played back anywhere
+ */
+pub fn setup_daily_run(
+    mut commands: Commands,
+    mut daily: ResMut<DailyRun>,
+    log: Res<DailyLog>,
+    wave: Option<Res<Wave>>,
+) {
+    // not a new run
+    if wave.is_some() {
+        return;
+    }
+
+    let challenge = &daily.challenge;
+    commands.insert_resource(RunSeed(challenge.seed));
+    commands.insert_resource(RunModifiers(challenge.modifiers.clone()));
+
+    match daily.attempt {
+        Attempt::Scored => {
+            if let Some(path) = log.replay_path(&challenge.date()) {
+                commands.insert_resource(Recording::new(path, challenge.seed));
+            }
+            daily.attempt = Attempt::Recording;
+        }
+        // the scored attempt was started over, it is over for good
+        Attempt::Recording => {
+            commands.remove_resource::<Recording>();
+            daily.attempt = Attempt::Practice;
+        }
+        Attempt::Practice => {}
+    }
+}
+
+// The scored attempt is kept with a code to share it by
+pub fn finish_daily_run(
+    mut daily: ResMut<DailyRun>,
+    mut log: ResMut<DailyLog>,
+    recording: Option<Res<Recording>>,
+    score: Res<Score>,
+    wave: Res<Wave>,
+) {
+    if daily.attempt != Attempt::Recording {
+        return;
+    }
+
+    let date = daily.challenge.date();
+    let code = recording.map(|recording| {
+        ShareCode {
+            day: daily.challenge.day,
+            score: score.0,
+            wave: wave.n + 1,
+            ticks: recording.ticks.clone(),
+        }
+        .encode()
+    });
+    if let Some(entry) = log.entries.iter_mut().find(|entry| entry.date == date) {
+        entry.score = Some(score.0);
+        entry.wave = Some(wave.n + 1);
+        entry.code = code.clone();
+    }
+    log.save();
+    daily.attempt = Attempt::Practice;
+    daily.code = code;
+}
+
+pub fn copy_share_code(
+    mut contexts: EguiContexts,
+    keyboard: Res<Input<KeyCode>>,
+    daily: Res<DailyRun>,
+) {
+    let Some(code) = &daily.code else {
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::C) {
+        contexts
+            .ctx_mut()
+            .output_mut(|output| output.copied_text = code.clone());
+    }
+}
+
+pub fn daily_result_ui(mut contexts: EguiContexts, daily: Res<DailyRun>, score: Res<Score>) {
+    let modifiers: Vec<&str> = daily
+        .challenge
+        .modifiers
+        .iter()
+        .map(|modifier| modifier.name())
+        .collect();
+    let result = if daily.code.is_some() {
+        format!("Scored {}   [C] Copy the share code", score.0)
+    } else {
+        "Practice, already played today".to_string()
+    };
+
+    egui::Area::new("daily result")
+        .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., 60.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new(format!("Daily Challenge {}", daily.challenge.date()))
+                        .font(FontId::proportional(18.))
+                        .color(Color32::YELLOW),
+                );
+                ui.label(
+                    RichText::new(modifiers.join(", "))
+                        .font(FontId::proportional(12.))
+                        .color(Color32::WHITE),
+                );
+                ui.label(
+                    RichText::new(result)
+                        .font(FontId::proportional(12.))
+                        .color(Color32::WHITE),
+                );
+            })
+        });
+}
+
+// Back to the rules of every other run
+pub fn leave_daily_run(mut commands: Commands) {
+    commands.remove_resource::<DailyRun>();
+    commands.remove_resource::<Recording>();
+    commands.insert_resource(RunModifiers::default());
+}
@@ -0,0 +1,51 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/**
+ * Where a JSON file is kept in the user's data directory, none where there is
+ * no file system (wasm). `what` names its contents in the error logs of
+ * `load` and `save`.
+ */
+pub fn path(file_name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("invasion").join(file_name))
+}
+
+// The saved value, or the default when there is none or it can't be read
+pub fn load<T: DeserializeOwned + Default>(path: Option<&Path>, what: &str) -> T {
+    path.filter(|path| path.exists())
+        .and_then(|path| match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|err| error!("Ignoring the broken {what} in {path:?}: {err}"))
+                .ok(),
+            Err(err) => {
+                error!("Could not read the {what} in {path:?}: {err}");
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+pub fn save<T: Serialize>(path: Option<&Path>, value: &T, what: &str) {
+    let Some(path) = path else {
+        return;
+    };
+    let text = match serde_json::to_string_pretty(value) {
+        Ok(text) => text,
+        Err(err) => {
+            error!("Could not serialize the {what}: {err}");
+            return;
+        }
+    };
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, text));
+    if let Err(err) = written {
+        error!("Could not save the {what} to {path:?}: {err}");
+    }
+}
@@ -0,0 +1,68 @@
+/**
+ * Calendar days of the local date, so a day turns over at the player's own
+ * midnight. The clock can't be read on wasm, where there is no day.
+ */
+#[cfg(not(target_arch = "wasm32"))]
+pub fn days_since_epoch() -> Option<i64> {
+    use chrono::{Local, NaiveDate};
+
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    Some((Local::now().date_naive() - epoch).num_days())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn days_since_epoch() -> Option<i64> {
+    None
+}
+
+// Today as YYYY-MM-DD, or an empty string where the clock can't be read
+pub fn today() -> String {
+    days_since_epoch().map_or(String::new(), format_day)
+}
+
+pub fn format_day(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Howard Hinnant's days to proleptic Gregorian calendar conversion
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_days_convert_to_their_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+    }
+
+    #[test]
+    fn leap_days_are_counted() {
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        // a century that is not a leap year goes from february to march
+        assert_eq!(civil_from_days(47_540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+    }
+
+    #[test]
+    fn days_are_formatted_with_padding() {
+        assert_eq!(format_day(11_016), "2000-02-29");
+        assert_eq!(format_day(0), "1970-01-01");
+    }
+}
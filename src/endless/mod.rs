@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+
+mod systems;
+use systems::*;
+
+use crate::{
+    config::Headless,
+    game::prelude::{GameTick, PauseState, Stage, TickSet},
+    GameState,
+};
+
+const ENDLESS_STAGE: &str = "endless.stage.json";
+// How long the name of a milestone is shown once it is reached
+const BANNER_SECS: f32 = 3.0;
+
+// Loads the stage endless runs are played on and announces the milestones
+// reached along the way
+pub struct EndlessPlugin;
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_endless_stage)
+            .add_systems(
+                GameTick,
+                announce_milestones
+                    .in_set(TickSet::Play)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                Update,
+                milestone_ui.run_if(
+                    in_state(GameState::InGame)
+                        .and_then(in_state(PauseState::Running))
+                        .and_then(resource_exists::<MilestoneBanner>())
+                        .and_then(not(resource_exists::<Headless>())),
+                ),
+            )
+            .add_systems(OnExit(GameState::InGame), teardown_banner);
+    }
+}
+
+// The stage of endless runs, kept out of the campaign's folder
+#[derive(Resource)]
+pub struct EndlessStage(pub Handle<Stage>);
+
+// The milestone just reached, shown for a while
+#[derive(Resource)]
+pub struct MilestoneBanner {
+    pub wave: usize,
+    pub name: String,
+    pub timer: Timer,
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32, FontId, RichText},
+    EguiContexts,
+};
+
+use crate::game::prelude::MilestoneEvent;
+
+use super::{EndlessStage, MilestoneBanner, BANNER_SECS, ENDLESS_STAGE};
+
+pub fn load_endless_stage(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EndlessStage(asset_server.load(ENDLESS_STAGE)));
+}
+
+pub fn announce_milestones(
+    mut commands: Commands,
+    mut milestones: EventReader<MilestoneEvent>,
+    banner: Option<ResMut<MilestoneBanner>>,
+    time: Res<Time>,
+) {
+    if let Some(last) = milestones.iter().last() {
+        commands.insert_resource(MilestoneBanner {
+            wave: last.wave,
+            name: last.name.clone(),
+            timer: Timer::from_seconds(BANNER_SECS, TimerMode::Once),
+        });
+        return;
+    }
+
+    if let Some(mut banner) = banner {
+        if banner.timer.tick(time.delta()).finished() {
+            commands.remove_resource::<MilestoneBanner>();
+        }
+    }
+}
+
+pub fn milestone_ui(mut contexts: EguiContexts, banner: Res<MilestoneBanner>) {
+    egui::Area::new("milestone")
+        .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., -60.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new(format!("WAVE {}", banner.wave + 1))
+                        .font(FontId::proportional(18.))
+                        .color(Color32::YELLOW),
+                );
+                ui.label(
+                    RichText::new(banner.name.clone())
+                        .font(FontId::proportional(24.))
+                        .color(Color32::WHITE),
+                );
+            })
+        });
+}
+
+pub fn teardown_banner(mut commands: Commands) {
+    commands.remove_resource::<MilestoneBanner>();
+}
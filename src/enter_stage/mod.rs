@@ -3,7 +3,7 @@ use bevy::prelude::*;
 mod systems;
 use systems::*;
 
-use crate::{game::prelude::stage_colors, GameState};
+use crate::{config::Headless, game::prelude::stage_colors, GameState};
 
 // Loads the level from json, setups level resources and show the
 // level introduction
@@ -14,10 +14,11 @@ impl Plugin for EnterStagePlugin {
             .add_systems(
                 Update,
                 (
-                    show_stage_intro,
+                    show_stage_intro.run_if(not(resource_exists::<Headless>())),
                     stage_intro_keys,
                     stage_intro_timer,
                     stage_colors,
+                    skip_stage_intro.run_if(resource_exists::<SkipStageIntro>()),
                 )
                     .run_if(in_state(GameState::EnterGame)),
             )
@@ -27,3 +28,8 @@ impl Plugin for EnterStagePlugin {
             );
     }
 }
+
+// Starts every playable stage without waiting for the player, for replays and
+// headless runs
+#[derive(Resource)]
+pub struct SkipStageIntro;
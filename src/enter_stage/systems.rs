@@ -1,76 +1,190 @@
 use std::time::Duration;
 
 use crate::{
-    game::prelude::{EnemySpawn, SplitTimer, Stage, StageHandle, Wave, WaveSpawnCount},
+    campaign::Campaign,
+    endless::EndlessStage,
+    game::prelude::{
+        reset_run, EnemySpawn, GameMode, RunSeed, ScriptPlayback, SplitTimer, Stage, StageHandle,
+        Wave, WaveSpawnCount, WaveStats,
+    },
     GameState,
 };
-use bevy::prelude::*;
+use bevy::{asset::LoadState, prelude::*};
 use bevy_egui::{
     egui::{self, Align2, Color32, FontId, RichText},
     EguiContexts,
 };
+use bevy_turborand::GlobalRng;
 
-pub fn setup_stage(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let loaded_stage = asset_server.load("stages/1.stage.json");
-    let stage = StageHandle(loaded_stage.clone());
-    commands.insert_resource(stage);
+pub fn setup_stage(
+    mut commands: Commands,
+    campaign: Res<Campaign>,
+    endless: Option<Res<EndlessStage>>,
+    mode: Res<GameMode>,
+) {
+    let stage = match (*mode, endless) {
+        (GameMode::Endless, Some(endless)) => endless.0.clone(),
+        _ => campaign.stage(),
+    };
+    commands.insert_resource(StageHandle(stage));
 }
 
 pub fn setup_resources(
     mut commands: Commands,
     stage: Res<StageHandle>,
     stages: Res<Assets<Stage>>,
+    wave: Option<Res<Wave>>,
+    run_seed: Res<RunSeed>,
 ) {
-    let stage = stages.get(&stage.0).unwrap();
+    // a stage that can't be played is left rather than set up
+    let Some(stage) = stages
+        .get(&stage.0)
+        .filter(|stage| stage.problems.is_empty())
+    else {
+        return;
+    };
+    // a new run starts over from the run seed, unless the stage has its own
+    if let Some(seed) = stage.seed.or(wave.is_none().then_some(run_seed.0)) {
+        commands.insert_resource(GlobalRng::with_seed(seed));
+    }
+
     let mut timer = Timer::new(Duration::from_secs(5), TimerMode::Once);
     timer.pause();
+    // the wave count carries over from the previous stage
+    let n = wave.map_or(0, |wave| wave.n);
     commands.insert_resource(Wave {
-        n: 0,
+        n,
+        first: n,
         completion_timeout: timer,
     });
     commands.insert_resource(EnemySpawn(Timer::new(
-        Duration::from_millis((stage.spawn_interval_secs(0) * 1000.0) as u64),
+        Duration::from_millis((stage.spawn_interval_secs(n) * 1000.0) as u64),
         TimerMode::Repeating,
     )));
     commands.insert_resource(SplitTimer(Timer::new(
-        Duration::from_millis((stage.split_interval_secs(0) * 1000.0) as u64),
+        Duration::from_millis((stage.split_interval_secs(n) * 1000.0) as u64),
         TimerMode::Repeating,
     )));
     commands.insert_resource(WaveSpawnCount(0));
+    commands.insert_resource(ScriptPlayback::default());
+    commands.insert_resource(WaveStats::default());
 }
 
 pub fn show_stage_intro(
     mut contexts: EguiContexts,
+    mode: Res<GameMode>,
+    asset_server: Res<AssetServer>,
     stage: Res<StageHandle>,
     stages: Res<Assets<Stage>>,
 ) {
-    if let Some(stage) = stages.get(&stage.0) {
-        egui::Area::new("title")
-            .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., -50.))
-            .show(contexts.ctx_mut(), |ui| {
-                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                    ui.label(
-                        RichText::new(stage.name.clone())
-                            .font(FontId::proportional(24.))
-                            .color(Color32::WHITE),
-                    );
+    let (name, bread, problems) = match stages.get(&stage.0) {
+        Some(stage) => (
+            stage.name.clone(),
+            stage.bread.clone(),
+            stage.problems.clone(),
+        ),
+        None if asset_server.get_load_state(&stage.0) == LoadState::Failed => (
+            asset_server
+                .get_handle_path(&stage.0)
+                .map_or(String::new(), |path| path.path().display().to_string()),
+            String::new(),
+            vec!["the stage file could not be loaded".to_string()],
+        ),
+        // still loading
+        None => return,
+    };
+    let skip = match *mode {
+        GameMode::Endless => "Press [Space] to go back to the menu",
+        GameMode::Campaign => "Press [Space] to skip it",
+    };
+
+    egui::Area::new("title")
+        .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., -50.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new(name)
+                        .font(FontId::proportional(24.))
+                        .color(Color32::WHITE),
+                );
+                ui.label(
+                    RichText::new(bread)
+                        .font(FontId::proportional(18.))
+                        .color(Color32::WHITE),
+                );
+                if problems.is_empty() {
+                    return;
+                }
+
+                ui.label(
+                    RichText::new("This stage can't be played:")
+                        .font(FontId::proportional(18.))
+                        .color(Color32::RED),
+                );
+                for problem in problems.iter() {
                     ui.label(
-                        RichText::new(stage.bread.clone())
-                            .font(FontId::proportional(18.))
-                            .color(Color32::WHITE),
+                        RichText::new(problem)
+                            .font(FontId::proportional(12.))
+                            .color(Color32::RED),
                     );
-                })
-            });
-    }
+                }
+                ui.label(
+                    RichText::new(skip)
+                        .font(FontId::proportional(18.))
+                        .color(Color32::WHITE),
+                );
+            })
+        });
 }
 
 pub fn stage_intro_keys(
+    mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
     keyboard: Res<Input<KeyCode>>,
+    mut campaign: ResMut<Campaign>,
+    mode: Res<GameMode>,
+    asset_server: Res<AssetServer>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
 ) {
-    if keyboard.just_released(KeyCode::Space) {
+    if !keyboard.just_released(KeyCode::Space) {
+        return;
+    }
+
+    let playable = match stages.get(&stage.0) {
+        Some(stage) => stage.problems.is_empty(),
+        // still loading
+        None if asset_server.get_load_state(&stage.0) != LoadState::Failed => return,
+        // a stage that could not be loaded is skipped like one with problems
+        None => false,
+    };
+    if playable {
         next_state.set(GameState::InGame);
+    } else if *mode == GameMode::Endless {
+        // endless has no other stage to go on to
+        reset_run(&mut commands);
+        next_state.set(GameState::MainMenu);
+    } else if let Some(next) = campaign.next(&asset_server) {
+        campaign.current = next;
+        commands.insert_resource(StageHandle(campaign.stage()));
+    } else {
+        // nothing left to skip to
+        reset_run(&mut commands);
+        next_state.set(GameState::MainMenu);
     }
 }
+pub fn skip_stage_intro(
+    mut next_state: ResMut<NextState<GameState>>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+) {
+    if stages
+        .get(&stage.0)
+        .map_or(false, |stage| stage.problems.is_empty())
+    {
+        next_state.set(GameState::InGame);
+    }
+}
+
 pub fn stage_intro_timer() {}
 pub fn teardown_stage() {}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::prelude::WaveStats;
+
+// The longest the bonus takes to count, however much there is left
+const TALLY_SECS: f32 = 3.0;
+const MAX_STEP_SECS: f32 = 0.15;
+
+/**
+ * The bonus for the wave just completed, counted into the score one city and
+ * one missile at a time while the next wave is on its way
+ */
+#[derive(Resource)]
+pub struct BonusTally {
+    pub cities: usize,
+    pub ammo: usize,
+    pub counted_cities: usize,
+    pub counted_ammo: usize,
+    // points for each, with the multiplier applied
+    pub city_points: usize,
+    pub ammo_points: usize,
+    pub multiplier: usize,
+    pub accuracy: Option<f32>,
+    pub largest_combo: usize,
+    pub step: Timer,
+}
+
+impl BonusTally {
+    pub fn new(
+        cities: usize,
+        ammo: usize,
+        city_bonus: usize,
+        ammo_bonus: usize,
+        multiplier: usize,
+        stats: &WaveStats,
+    ) -> Self {
+        let items = (cities + ammo).max(1) as f32;
+        let step_secs = (TALLY_SECS / items).min(MAX_STEP_SECS);
+
+        Self {
+            cities,
+            ammo,
+            counted_cities: 0,
+            counted_ammo: 0,
+            city_points: city_bonus * multiplier,
+            ammo_points: ammo_bonus * multiplier,
+            multiplier,
+            accuracy: stats.accuracy(),
+            largest_combo: stats.largest_combo,
+            step: Timer::new(Duration::from_secs_f32(step_secs), TimerMode::Repeating),
+        }
+    }
+
+    // the points of the next city or missile to count, cities first
+    pub fn next(&mut self) -> Option<usize> {
+        if self.counted_cities < self.cities {
+            self.counted_cities += 1;
+            Some(self.city_points)
+        } else if self.counted_ammo < self.ammo {
+            self.counted_ammo += 1;
+            Some(self.ammo_points)
+        } else {
+            None
+        }
+    }
+
+    pub fn counted(&self) -> usize {
+        self.counted_cities * self.city_points + self.counted_ammo * self.ammo_points
+    }
+
+    pub fn total(&self) -> usize {
+        self.cities * self.city_points + self.ammo * self.ammo_points
+    }
+
+    // counts what is left all at once, the points of it
+    pub fn finish(&mut self) -> usize {
+        let left = self.total() - self.counted();
+        self.counted_cities = self.cities;
+        self.counted_ammo = self.ammo;
+        left
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.counted_cities == self.cities && self.counted_ammo == self.ammo
+    }
+}
@@ -10,6 +10,28 @@ pub fn circles_touching(a: &Transform, ar: &Bounding, b: &Transform, br: &Boundi
     a.translation.distance(b.translation) < (ar.0 + br.0)
 }
 
+/**
+ * Whether a circle that moved from `from` to `to` touched a resting one
+ * anywhere along the way, so nothing slips through between two checks
+ */
+pub fn swept_circles_touching(
+    from: &Vec3,
+    to: &Vec3,
+    ar: &Bounding,
+    b: &Transform,
+    br: &Bounding,
+) -> bool {
+    let path = *to - *from;
+    let length = path.length_squared();
+    // how far along the path it came closest to the resting circle
+    let t = if length > 0.0 {
+        ((b.translation - *from).dot(path) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    distance_between(&(*from + path * t), &b.translation) <= ar.0 + br.0
+}
+
 pub fn distance_to_move(a: &Vec3, ar: f32, b: &Vec3, br: f32) -> f32 {
     ar + br - distance_between(a, b)
 }
@@ -22,3 +44,83 @@ pub fn rotate_point(fp: Vec2, pt: Vec2, a: f32) -> Vec2 {
 
     vec2(fp.x + x_rot, fp.y + y_rot)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flame_at(x: f32, y: f32) -> Transform {
+        Transform::from_xyz(x, y, 0.0)
+    }
+
+    #[test]
+    fn path_through_the_circle_touches_with_both_ends_outside() {
+        let from = Vec3::new(0.0, 100.0, 0.0);
+        let to = Vec3::new(0.0, -100.0, 0.0);
+        assert!(from.distance(Vec3::ZERO) > 16.0 && to.distance(Vec3::ZERO) > 16.0);
+        assert!(swept_circles_touching(
+            &from,
+            &to,
+            &Bounding(2.0),
+            &flame_at(0.0, 0.0),
+            &Bounding(16.0),
+        ));
+    }
+
+    #[test]
+    fn zero_length_path_is_a_resting_circle() {
+        let at = Vec3::new(10.0, 0.0, 0.0);
+        assert!(swept_circles_touching(
+            &at,
+            &at,
+            &Bounding(2.0),
+            &flame_at(0.0, 0.0),
+            &Bounding(16.0),
+        ));
+        let at = Vec3::new(20.0, 0.0, 0.0);
+        assert!(!swept_circles_touching(
+            &at,
+            &at,
+            &Bounding(2.0),
+            &flame_at(0.0, 0.0),
+            &Bounding(16.0),
+        ));
+    }
+
+    #[test]
+    fn path_grazing_the_circle_touches() {
+        // passes exactly the two radii away from the centre
+        let from = Vec3::new(-100.0, 18.0, 0.0);
+        let to = Vec3::new(100.0, 18.0, 0.0);
+        assert!(swept_circles_touching(
+            &from,
+            &to,
+            &Bounding(2.0),
+            &flame_at(0.0, 0.0),
+            &Bounding(16.0),
+        ));
+    }
+
+    #[test]
+    fn path_missing_the_circle_does_not_touch() {
+        let from = Vec3::new(-100.0, 19.0, 0.0);
+        let to = Vec3::new(100.0, 19.0, 0.0);
+        assert!(!swept_circles_touching(
+            &from,
+            &to,
+            &Bounding(2.0),
+            &flame_at(0.0, 0.0),
+            &Bounding(16.0),
+        ));
+        // heading straight for it, but stopping short
+        let from = Vec3::new(0.0, 100.0, 0.0);
+        let to = Vec3::new(0.0, 30.0, 0.0);
+        assert!(!swept_circles_touching(
+            &from,
+            &to,
+            &Bounding(2.0),
+            &flame_at(0.0, 0.0),
+            &Bounding(16.0),
+        ));
+    }
+}
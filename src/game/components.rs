@@ -1,15 +1,36 @@
-use std::time::Duration;
+use std::{f32::consts::PI, time::Duration};
 
 use bevy::{prelude::*, utils::HashSet};
 use derive_more::From;
 
+use super::prelude::FlyerKind;
+
 pub const PLAYER_MISSILE_SPEED: f32 = 250.0;
 pub const MAX_AMMO: u8 = 30;
 pub const CITY_RESTORATION_POINTS: usize = 10000;
+// How far the flames of an explosion reach at every step of its animation
+pub const FLAME_RADIUS_STEPS: [i32; 10] = [2, 8, 12, 16, 16, 16, 12, 12, 8, 2];
+pub const FLAME_STEP_SECS: f32 = 0.1;
+// How far past the flames a smart bomb sees them coming, and how fast it gets out of the way
+pub const SMART_BOMB_SIGHT: f32 = 24.0;
+pub const SMART_BOMB_EVADE_SPEED: f32 = 60.0;
+// A mirv flashes for this long before it splits
+pub const MIRV_TELEGRAPH_SECS: f32 = 1.0;
+// Hits a city and a battery take before they are destroyed
+pub const CITY_HEALTH: u8 = 2;
+pub const TANK_HEALTH: u8 = 3;
+// How far from where an enemy missile lands it does damage, the widest flame and half a city
+pub const IMPACT_RADIUS: f32 = 32.0;
+// How close the flames have to come to take an enemy, the head of a missile and the hull of a flyer
+pub const MISSILE_RADIUS: f32 = 2.0;
+pub const FLYER_RADIUS: f32 = 6.0;
 
 #[derive(From)]
 pub enum Scoring {
     Missile = 50,
+    SmartBomb = 125,
+    // only for a mirv taken out before it splits
+    Mirv = 250,
     Ufo = 1000,
 }
 
@@ -28,10 +49,78 @@ pub enum TankBody {
     Intact,
     Destroyed,
 }
+// A missile battery, sits on a tank body and fires from its own reserve
 #[derive(Component)]
-pub struct Ufo(pub Vec2);
+pub struct Battery {
+    pub index: usize,
+    // the reserve is refilled to this every wave
+    pub ammo: u8,
+    pub cooldown: Timer,
+}
+// A flying enemy, ufos, bombers and satellites alike
+#[derive(Component)]
+pub struct Flyer {
+    pub kind: FlyerKind,
+    pub path: FlightPath,
+    pub speed: f32,
+    pub score: usize,
+    // the chance of turning back, checked every time the timer finishes
+    pub turn_chance: f64,
+    pub turn_timer: Timer,
+}
+
+pub enum FlightPath {
+    // straight on to a point on the other side of the screen
+    Straight(Vec2),
+    // over a hump `height` above the line from one side to the other
+    Arc {
+        from: Vec2,
+        to: Vec2,
+        height: f32,
+        progress: f32,
+    },
+}
+
+impl Flyer {
+    pub fn velocity(&self, pos: Vec2) -> Vec2 {
+        match self.path {
+            FlightPath::Straight(to) => (to - pos).normalize_or_zero() * self.speed,
+            FlightPath::Arc {
+                from,
+                to,
+                height,
+                progress,
+            } => {
+                let rate = self.speed / from.distance(to).max(f32::EPSILON);
+                let climb = height * PI * (PI * progress).cos();
+                ((to - from) + Vec2::Y * climb) * rate
+            }
+        }
+    }
+
+    // seconds until the flyer is off the screen
+    pub fn remaining_secs(&self, pos: Vec2) -> f32 {
+        let distance = match self.path {
+            FlightPath::Straight(to) => to.distance(pos),
+            FlightPath::Arc {
+                from, to, progress, ..
+            } => from.distance(to) * (1.0 - progress),
+        };
+        distance / self.speed.max(f32::EPSILON)
+    }
+}
 #[derive(Component)]
 pub struct Enemy;
+// A missile that steers around explosions in its way
+#[derive(Component)]
+pub struct SmartBomb;
+// A missile that splits into warheads for different cities at `split_y`
+#[derive(Component)]
+pub struct Mirv {
+    pub split_y: f32,
+    pub warheads: u8,
+    pub telegraphed: bool,
+}
 #[derive(Component)]
 pub struct HealthBar;
 #[derive(Component)]
@@ -39,6 +128,22 @@ pub struct Health {
     pub current: u8,
     pub max: u8,
 }
+
+impl Health {
+    pub fn full(max: u8) -> Self {
+        Self { current: max, max }
+    }
+
+    // takes a hit, true once there is nothing left
+    pub fn hit(&mut self) -> bool {
+        self.current = self.current.saturating_sub(1);
+        self.current == 0
+    }
+
+    pub fn is_damaged(&self) -> bool {
+        self.current < self.max
+    }
+}
 #[derive(Component)]
 pub struct SpawnPoint(pub Vec2);
 #[derive(Debug, Component, From)]
@@ -119,18 +224,25 @@ pub struct Explosion {
     pub score: usize,
     pub combo: usize,
     pub mode: ExplosionMode,
+    // set off by a missile of the player, rather than by what it hit
+    pub shot: bool,
 }
 impl Explosion {
-    pub fn new(mode: ExplosionMode) -> Self {
+    pub fn new(mode: ExplosionMode, shot: bool) -> Self {
         Self {
             score: 0,
             combo: 0,
             mode,
+            shot,
         }
     }
 
     pub fn add_score(&mut self, score: Scoring) {
-        self.score += score as usize;
+        self.add_points(score as usize);
+    }
+
+    pub fn add_points(&mut self, points: usize) {
+        self.score += points;
         self.combo += 1;
     }
 
@@ -143,6 +255,9 @@ impl Explosion {
 pub struct IdCounter(pub usize);
 #[derive(Resource)]
 pub struct Score(pub usize);
+// The battery moved by the movement keys
+#[derive(Resource)]
+pub struct ActiveBattery(pub usize);
 
 impl IdCounter {
     pub fn next(&mut self) -> usize {
@@ -167,6 +282,7 @@ pub struct MissileArrivalEvent {
 pub struct ExplosionEvent {
     pub pos: Vec3,
     pub mode: ExplosionMode,
+    pub shot: bool,
 }
 
 #[derive(Event)]
@@ -176,17 +292,31 @@ pub struct ScoreGainedEvent {
 }
 
 #[derive(Event)]
-pub struct TankDestroyedEvent;
+pub struct TankDestroyedEvent(pub Entity);
+
+#[derive(Event)]
+pub struct FlyerDestroyedEvent {
+    pub pos: Vec3,
+    pub kind: FlyerKind,
+}
 
 #[derive(Component)]
 pub struct Foreground;
 #[derive(Component)]
 pub struct TextColor;
 #[derive(Component)]
-pub struct DropBombTimer(pub Timer);
+pub struct DropBombTimer {
+    pub timer: Timer,
+    // bombs left to drop
+    pub bombs: u8,
+    // the chance of dropping one every time the timer finishes
+    pub chance: f64,
+}
 #[derive(Component)]
 pub struct MissileReserve(pub u8);
 #[derive(Component)]
 pub struct City;
 #[derive(Component)]
 pub struct Destroyed;
+#[derive(Component)]
+pub struct Ground;
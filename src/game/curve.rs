@@ -0,0 +1,249 @@
+use serde::Deserialize;
+
+/**
+ * A stage parameter evaluated per wave, e.g.
+ * `{ "linear": { "base": 20.0, "rate": 4.5 }, "max": 50.0 }`
+ */
+#[derive(Deserialize, Clone, Debug)]
+pub struct Curve {
+    #[serde(flatten)]
+    pub shape: Shape,
+    #[serde(default)]
+    pub min: Option<f32>,
+    #[serde(default)]
+    pub max: Option<f32>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    Constant(f32),
+    // base + rate * wave
+    Linear { base: f32, rate: f32 },
+    // base * factor ^ wave
+    Exponential { base: f32, factor: f32 },
+    // base + rate for every `every` waves
+    Step { base: f32, rate: f32, every: usize },
+    // scale / (base + rate * wave)
+    Inverse { scale: f32, base: f32, rate: f32 },
+    // [wave, value] points, interpolated between and held past the ends
+    Table(Vec<[f32; 2]>),
+}
+
+impl Curve {
+    pub fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            min: None,
+            max: None,
+        }
+    }
+
+    pub fn with_min(mut self, min: f32) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn with_max(mut self, max: f32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn at(&self, wave: usize) -> f32 {
+        let w = wave as f32;
+        let value = match &self.shape {
+            Shape::Constant(value) => *value,
+            Shape::Linear { base, rate } => base + rate * w,
+            Shape::Exponential { base, factor } => base * factor.powf(w),
+            Shape::Step { base, rate, every } => base + rate * (wave / (*every).max(1)) as f32,
+            Shape::Inverse { scale, base, rate } => scale / (base + rate * w),
+            Shape::Table(points) => interpolate(points, w),
+        };
+
+        let value = self.min.map_or(value, |min| f32::max(min, value));
+        self.max.map_or(value, |max| f32::min(max, value))
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match &self.shape {
+            Shape::Step { every: 0, .. } => problems.push("step every must be at least 1".into()),
+            Shape::Table(points) if points.is_empty() => {
+                problems.push("table needs at least one [wave, value] point".into())
+            }
+            Shape::Table(points) if points.windows(2).any(|pair| pair[0][0] >= pair[1][0]) => {
+                problems.push("table waves must be in increasing order".into())
+            }
+            _ => {}
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                problems.push(format!("min ({min}) is greater than max ({max})"));
+            }
+        }
+
+        problems
+    }
+}
+
+fn interpolate(points: &[[f32; 2]], w: f32) -> f32 {
+    let Some(first) = points.first() else {
+        return 0.0;
+    };
+    if w <= first[0] {
+        return first[1];
+    }
+
+    for pair in points.windows(2) {
+        let ([from_wave, from], [to_wave, to]) = (pair[0], pair[1]);
+        if w <= to_wave {
+            return from + (to - from) * (w - from_wave) / (to_wave - from_wave);
+        }
+    }
+
+    points[points.len() - 1][1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::prelude::Stage;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {expected}, found {actual}"
+        );
+    }
+
+    #[test]
+    fn constant_is_the_same_every_wave() {
+        let curve = Curve::new(Shape::Constant(3.0));
+        for wave in [0, 1, 50] {
+            assert_close(curve.at(wave), 3.0);
+        }
+    }
+
+    #[test]
+    fn linear_adds_its_rate_every_wave() {
+        let curve = Curve::new(Shape::Linear {
+            base: 1.0,
+            rate: 0.5,
+        });
+        assert_close(curve.at(0), 1.0);
+        assert_close(curve.at(4), 3.0);
+    }
+
+    #[test]
+    fn exponential_multiplies_by_its_factor_every_wave() {
+        let curve = Curve::new(Shape::Exponential {
+            base: 2.0,
+            factor: 1.5,
+        });
+        assert_close(curve.at(0), 2.0);
+        assert_close(curve.at(2), 4.5);
+    }
+
+    #[test]
+    fn step_adds_its_rate_every_few_waves() {
+        let curve = Curve::new(Shape::Step {
+            base: 1.0,
+            rate: 2.0,
+            every: 3,
+        });
+        assert_close(curve.at(2), 1.0);
+        assert_close(curve.at(3), 3.0);
+        assert_close(curve.at(7), 5.0);
+
+        // a step of no waves is taken every wave rather than dividing by zero
+        let curve = Curve::new(Shape::Step {
+            base: 1.0,
+            rate: 2.0,
+            every: 0,
+        });
+        assert_close(curve.at(2), 5.0);
+    }
+
+    #[test]
+    fn inverse_divides_its_scale() {
+        let curve = Curve::new(Shape::Inverse {
+            scale: 6.0,
+            base: 1.0,
+            rate: 1.0,
+        });
+        assert_close(curve.at(0), 6.0);
+        assert_close(curve.at(2), 2.0);
+    }
+
+    #[test]
+    fn table_interpolates_and_holds_past_the_ends() {
+        let curve = Curve::new(Shape::Table(vec![[2.0, 0.0], [4.0, 8.0], [6.0, 2.0]]));
+        assert_close(curve.at(0), 0.0);
+        assert_close(curve.at(3), 4.0);
+        assert_close(curve.at(5), 5.0);
+        assert_close(curve.at(10), 2.0);
+
+        assert_close(Curve::new(Shape::Table(Vec::new())).at(3), 0.0);
+    }
+
+    #[test]
+    fn min_and_max_clamp_the_value() {
+        let curve = Curve::new(Shape::Linear {
+            base: 0.0,
+            rate: 10.0,
+        })
+        .with_min(5.0)
+        .with_max(25.0);
+        assert_close(curve.at(0), 5.0);
+        assert_close(curve.at(1), 10.0);
+        assert_close(curve.at(3), 25.0);
+    }
+
+    #[test]
+    fn problems_are_found() {
+        let step = Curve::new(Shape::Step {
+            base: 1.0,
+            rate: 1.0,
+            every: 0,
+        });
+        assert_eq!(step.problems().len(), 1);
+        let table = Curve::new(Shape::Table(vec![[2.0, 0.0], [1.0, 1.0]]));
+        assert_eq!(table.problems().len(), 1);
+        let clamp = Curve::new(Shape::Constant(1.0)).with_min(2.0).with_max(1.0);
+        assert_eq!(clamp.problems().len(), 1);
+        assert!(Curve::new(Shape::Constant(1.0)).problems().is_empty());
+    }
+
+    // the formulas every stage was played with before curves, with the default values
+    #[test]
+    fn defaults_match_the_formulas_they_replaced() {
+        let stage = Stage::default();
+        let (base, rate) = (1.0_f32, 0.1_f32);
+        for wave in 0..40 {
+            let w = wave as f32;
+            assert_close(
+                stage.spawn_interval_secs(wave),
+                f32::max(1.75, 2.7 - w / 10.0),
+            );
+            assert_close(
+                stage.split_interval_secs(wave),
+                f32::max(1.5, 2.7 / (base + w * rate)),
+            );
+            assert_eq!(stage.enemies_count(wave), 12 + wave * 2);
+            assert_eq!(stage.missile_spawn_min(wave), 0);
+            assert_eq!(stage.missile_spawn_max(wave), 1 + wave / 10);
+            assert_close(stage.missile_speed(wave), f32::min(50.0, 20.0 + w * 4.5));
+            assert_close(stage.ufo_speed(wave), f32::min(50.0, 25.0 + w * base * 3.3));
+            assert_close(stage.drop_bomb_chance(wave) as f32, 0.1 + (w / 10.0) * rate);
+            assert_close(
+                stage.ufo_chance(wave) as f32,
+                f32::min(1.0, 0.1 + (w / 10.0) * rate),
+            );
+            assert_close(
+                stage.split_chance(wave) as f32,
+                f32::min(1.0, 0.05 + 0.01 * w),
+            );
+            assert_eq!(stage.max_split(wave), 2 + (wave / 10) as u8);
+        }
+    }
+}
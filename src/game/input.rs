@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::MainCamera;
+
+use super::modifiers::{Modifier, RunModifiers};
+
+/**
+ * What the player does this tick. Gameplay systems read this instead of the
+ * devices so that inputs can be recorded and played back.
+ */
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub struct PlayerInput {
+    // the sight in world coordinates
+    pub cursor: Vec2,
+    pub fire: bool,
+    // fire from this battery instead of the closest one
+    pub battery: Option<u8>,
+    pub left: bool,
+    pub right: bool,
+}
+
+#[derive(Resource, Default, PartialEq, Eq, Debug)]
+pub enum InputSource {
+    #[default]
+    Devices,
+    Replay,
+    // a policy of the headless simulation
+    Bot,
+    Autopilot,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub struct InputSet;
+
+// Fires from the battery of the same number
+const BATTERY_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+pub fn read_device_input(
+    mut input: ResMut<PlayerInput>,
+    buttons: Res<Input<MouseButton>>,
+    keyboard: Res<Input<KeyCode>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    modifiers: Res<RunModifiers>,
+) {
+    // read as if the screen were turned around
+    let mirror = modifiers.is_active(Modifier::Mirror);
+    // a press waits for the next tick, which uses it up
+    if let Some(index) = BATTERY_KEYS
+        .iter()
+        .position(|key| keyboard.just_pressed(*key))
+    {
+        input.battery = Some(index as u8);
+        input.fire = true;
+    }
+    input.fire |= buttons.just_pressed(MouseButton::Left);
+    input.left = keyboard.any_pressed([KeyCode::A, KeyCode::Left]);
+    input.right = keyboard.any_pressed([KeyCode::D, KeyCode::Right]);
+    if mirror {
+        std::mem::swap(&mut input.left, &mut input.right);
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (windows.get_single(), camera_q.get_single())
+    else {
+        return;
+    };
+    // the sight stays where it was while the mouse is outside the window
+    if let Some(world_position) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    {
+        input.cursor = if mirror {
+            Vec2::new(-world_position.x, world_position.y)
+        } else {
+            world_position
+        };
+    }
+}
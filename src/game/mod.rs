@@ -1,52 +1,104 @@
 use std::time::Duration;
 
 use self::{
+    bonus::BonusTally,
     components::{
-        ExplosionEvent, IdCounter, MissileArrivalEvent, PhysicsSet, Score, ScoreGainedEvent,
-        TankDestroyedEvent,
+        ExplosionEvent, FlyerDestroyedEvent, IdCounter, MissileArrivalEvent, PhysicsSet, Score,
+        ScoreGainedEvent, TankDestroyedEvent,
     },
     effects::{flick_system, timed_removal_system},
-    prelude::stage_colors,
+    input::{read_device_input, InputSet, InputSource, PlayerInput},
+    modifiers::{speed_up_enemies, RunModifiers},
+    power_ups::{
+        drop_power_ups, fall_power_ups, pick_up_power_ups, teardown_power_ups, tick_power_ups,
+        ActivePowerUps,
+    },
+    prelude::{stage_colors, GameMode, MilestoneEvent, PauseState, Stage, WaveCompletedEvent},
+    schema::StageLoader,
     systems::{
-        ammo_ui, animate_sprite_indices, animate_sprite_steps, change_colors, defeat, despawns,
-        drop_bombs, explode_city, explosion_event_listener_system, explosion_system,
-        flame_engulf_system, game_keys, game_over_ui, gizmo_missile_trails, is_wave_finished,
-        missile_arrival_event_listner, move_cursor, move_missile, move_ufo, player_destruction,
-        reset_game_listener, rotate_player, score_gained_event_listener, score_ui, setup_player,
-        spawn_enemies, split_missiles, teardown_game_over, teardown_in_game, tick_wave_completion,
-        wave_complete, wave_complete_message_ui, wave_ui,
+        ammo_ui, animate_sprite_indices, animate_sprite_steps, change_colors, count_spawns,
+        damage_on_impact, defeat, despawns, drop_bombs, evade_explosions,
+        explosion_event_listener_system, explosion_system, flame_engulf_system, game_keys,
+        game_over_ui, gizmo_missile_trails, is_wave_finished, is_wave_scripted,
+        missile_arrival_event_listner, modifiers_ui, move_cursor, move_flyers, move_missile,
+        play_wave_script, player_destruction, reset_game_listener, rotate_player,
+        score_gained_event_listener, score_ui, setup_player, spawn_enemies, split_mirvs,
+        split_missiles, tally_bonus, teardown_game_over, teardown_in_game, teardown_scenery,
+        tick_wave_completion, wave_complete, wave_complete_message_ui, wave_ui,
+    },
+    tick::{
+        interpolate_positions, play_ticks, release_presses, reset_game_clock, restore_positions,
+        store_positions, GameClock, GameTick, TickEventExt, TickSet,
     },
+    upgrades::{RunUpgrades, UpgradeList, UpgradesLoader},
 };
-use crate::GameState;
-use bevy::prelude::*;
+use crate::{config::Headless, high_scores::InitialsEntry, GameState};
+use bevy::{prelude::*, transform::TransformSystem};
 
+mod bonus;
 mod collision;
 mod components;
+mod curve;
 mod effects;
+mod input;
+mod modifiers;
+mod power_ups;
 pub mod prelude;
+mod schema;
+mod script;
 mod systems;
+mod tick;
+mod upgrades;
 
 pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<MissileArrivalEvent>()
-            .add_event::<ExplosionEvent>()
-            .add_event::<TankDestroyedEvent>()
-            .add_event::<ScoreGainedEvent>()
-            .add_systems(OnEnter(GameState::InGame), setup_player)
+        app.add_state::<PauseState>()
+            .add_asset::<Stage>()
+            .init_asset_loader::<StageLoader>()
+            .add_asset::<UpgradeList>()
+            .init_asset_loader::<UpgradesLoader>()
+            .add_tick_event::<MissileArrivalEvent>()
+            .add_tick_event::<ExplosionEvent>()
+            .add_tick_event::<TankDestroyedEvent>()
+            .add_tick_event::<FlyerDestroyedEvent>()
+            .add_tick_event::<ScoreGainedEvent>()
+            .add_tick_event::<WaveCompletedEvent>()
+            .add_tick_event::<MilestoneEvent>()
+            .add_systems(
+                OnEnter(GameState::InGame),
+                (setup_player, reset_game_clock, release_presses),
+            )
             .add_systems(
                 Update,
                 (
-                    // always run these systems
+                    // presses are kept until a tick plays them
+                    read_device_input
+                        .before(play_ticks)
+                        .run_if(resource_equals(InputSource::Devices)),
+                    play_ticks.run_if(
+                        in_state(GameState::InGame)
+                            .or_else(in_state(GameState::GameOver))
+                            .and_then(in_state(PauseState::Running)),
+                    ),
+                )
+                    // nobody plays while the game is paused
+                    .run_if(in_state(PauseState::Running)),
+            )
+            // every stage is set up with fresh cities
+            .add_systems(OnEnter(GameState::EnterGame), teardown_scenery)
+            // nothing of a run is left on the menu
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                (teardown_scenery, teardown_game_over),
+            )
+            .add_systems(
+                GameTick,
+                (
                     (
-                        move_cursor,
                         animate_sprite_steps,
                         animate_sprite_indices,
-                        score_ui,
-                        ammo_ui,
-                        wave_ui,
                         tick_wave_completion,
-                        wave_complete_message_ui,
                     )
                         .run_if(in_state(GameState::InGame).or_else(in_state(GameState::GameOver))),
                     // run these systems if we are in the InGame state
@@ -56,48 +108,126 @@ impl Plugin for GamePlugin {
                         change_colors,
                         (
                             spawn_enemies.run_if(
-                                in_state(GameState::InGame).and_then(not(is_wave_finished)),
+                                in_state(GameState::InGame)
+                                    .and_then(not(is_wave_finished))
+                                    .and_then(not(is_wave_scripted)),
+                            ),
+                            play_wave_script.run_if(
+                                in_state(GameState::InGame)
+                                    .and_then(not(is_wave_finished))
+                                    .and_then(is_wave_scripted),
                             ),
-                            split_missiles,
+                            split_missiles.run_if(not(is_wave_scripted)),
+                            evade_explosions,
                             move_missile,
-                            gizmo_missile_trails,
-                            move_ufo,
-                            drop_bombs,
+                            split_mirvs,
+                            move_flyers,
+                            drop_bombs.run_if(not(is_wave_scripted)),
                             timed_removal_system,
                             missile_arrival_event_listner,
+                            damage_on_impact,
                             explosion_event_listener_system,
                             explosion_system,
                             flame_engulf_system,
                             score_gained_event_listener,
                             player_destruction,
-                            explode_city,
                             despawns,
                         )
                             .chain(),
+                        speed_up_enemies
+                            .after(split_missiles)
+                            .before(evade_explosions),
                         rotate_player,
                         defeat,
+                        count_spawns,
+                        tally_bonus
+                            .before(score_gained_event_listener)
+                            .run_if(resource_exists::<BonusTally>()),
+                        (
+                            drop_power_ups.after(flame_engulf_system),
+                            fall_power_ups,
+                            pick_up_power_ups,
+                            tick_power_ups,
+                        )
+                            .chain(),
                         stage_colors.after(spawn_enemies),
                         (wave_complete)
                             .run_if(in_state(GameState::InGame).and_then(is_wave_finished)),
                     )
                         .run_if(in_state(GameState::InGame)),
-                    // run these systems if we are in the GameOver state
+                    // the last missiles of the run still fly and explode
                     (
-                        game_over_ui,
-                        reset_game_listener,
                         move_missile,
                         explosion_event_listener_system,
                         explosion_system,
                     )
                         .run_if(in_state(GameState::GameOver)),
+                )
+                    .in_set(TickSet::Play),
+            )
+            .add_systems(GameTick, restore_positions.in_set(TickSet::Restore))
+            .add_systems(
+                GameTick,
+                (store_positions, release_presses).in_set(TickSet::Store),
+            )
+            .add_systems(
+                Update,
+                (
+                    // always run these systems
+                    (
+                        move_cursor,
+                        // nothing to draw the ui on without a window
+                        (
+                            score_ui,
+                            ammo_ui,
+                            wave_ui,
+                            wave_complete_message_ui,
+                            modifiers_ui,
+                        )
+                            .run_if(not(resource_exists::<Headless>())),
+                    )
+                        .run_if(in_state(GameState::InGame).or_else(in_state(GameState::GameOver))),
+                    gizmo_missile_trails.run_if(
+                        in_state(GameState::InGame)
+                            .and_then(in_state(PauseState::Running))
+                            .and_then(not(resource_exists::<Headless>())),
+                    ),
+                    // run these systems if we are in the GameOver state
+                    (
+                        game_over_ui.run_if(not(resource_exists::<Headless>())),
+                        // the initials are typed with the same keys
+                        reset_game_listener.run_if(not(resource_exists::<InitialsEntry>())),
+                    )
+                        .run_if(in_state(GameState::GameOver)),
                 ),
             )
-            .add_systems(OnExit(GameState::InGame), teardown_in_game)
+            // drawn between the ticks, before the positions reach the children
+            .add_systems(
+                PostUpdate,
+                interpolate_positions
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::InGame).or_else(in_state(GameState::GameOver))),
+            )
+            .add_systems(
+                OnExit(GameState::InGame),
+                (teardown_in_game, teardown_power_ups),
+            )
             .add_systems(OnExit(GameState::GameOver), teardown_game_over)
+            .configure_sets(
+                GameTick,
+                (TickSet::Restore, InputSet, TickSet::Play, TickSet::Store).chain(),
+            )
             .configure_set(
-                Update,
+                GameTick,
                 PhysicsSet::Movement.before(PhysicsSet::CollisionDetection),
             )
+            .init_resource::<GameClock>()
+            .init_resource::<PlayerInput>()
+            .init_resource::<InputSource>()
+            .init_resource::<GameMode>()
+            .init_resource::<ActivePowerUps>()
+            .init_resource::<RunUpgrades>()
+            .init_resource::<RunModifiers>()
             .insert_resource(IdCounter(0))
             .insert_resource(Score(0));
     }
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    components::{Enemy, Flyer, Missile},
+    prelude::BatteryLayout,
+};
+
+// How much faster enemy missiles fly with `FastMissiles`
+const FAST_MISSILES_SCALE: f32 = 1.5;
+// How much faster every enemy flies with `DoubleSpeed`
+const DOUBLE_SPEED_SCALE: f32 = 2.0;
+// How much wider the flames of a shot are with `HugeExplosions`
+const HUGE_EXPLOSIONS_SCALE: f32 = 2.0;
+// The city left standing with `OneCity`, the third from the left
+const ONE_CITY_SLOT: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Modifier {
+    // every battery holds half its missiles
+    HalfAmmo,
+    // enemy missiles fly faster
+    FastMissiles,
+    // points don't rebuild destroyed cities
+    NoCityRestoration,
+    // every enemy, missiles and ufos, flies twice as fast
+    DoubleSpeed,
+    // a single city to defend
+    OneCity,
+    // the batteries aren't refilled between waves
+    LimitedAmmo,
+    // the flames of a shot are twice as wide
+    HugeExplosions,
+    // the sight and the tanks move the other way
+    Mirror,
+    // the trails of enemy missiles aren't drawn
+    Fog,
+}
+
+impl Modifier {
+    // in the order they are listed on the menu
+    pub const ALL: [Modifier; 9] = [
+        Modifier::DoubleSpeed,
+        Modifier::OneCity,
+        Modifier::LimitedAmmo,
+        Modifier::HugeExplosions,
+        Modifier::Mirror,
+        Modifier::Fog,
+        Modifier::HalfAmmo,
+        Modifier::FastMissiles,
+        Modifier::NoCityRestoration,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Modifier::HalfAmmo => "Half Ammo",
+            Modifier::FastMissiles => "Fast Missiles",
+            Modifier::NoCityRestoration => "No Restoration",
+            Modifier::DoubleSpeed => "Double Speed",
+            Modifier::OneCity => "One City",
+            Modifier::LimitedAmmo => "Limited Ammo",
+            Modifier::HugeExplosions => "Huge Explosions",
+            Modifier::Mirror => "Mirror",
+            Modifier::Fog => "Fog",
+        }
+    }
+
+    // what the final score of a run is worth with this modifier, in percent
+    pub fn score_percent(&self) -> usize {
+        match self {
+            Modifier::HalfAmmo => 125,
+            Modifier::FastMissiles => 125,
+            Modifier::NoCityRestoration => 125,
+            Modifier::DoubleSpeed => 150,
+            Modifier::OneCity => 150,
+            Modifier::LimitedAmmo => 150,
+            Modifier::HugeExplosions => 50,
+            Modifier::Mirror => 125,
+            Modifier::Fog => 125,
+        }
+    }
+}
+
+/**
+ * The rules a run is played with on top of its stage. Kept across retries,
+ * whoever starts a run sets them.
+ */
+#[derive(Resource, Default, Clone, Debug)]
+pub struct RunModifiers(pub Vec<Modifier>);
+
+impl RunModifiers {
+    pub fn is_active(&self, modifier: Modifier) -> bool {
+        self.0.contains(&modifier)
+    }
+
+    // turns a modifier on or off, from the menu
+    pub fn toggle(&mut self, modifier: Modifier) {
+        if self.is_active(modifier) {
+            self.0.retain(|active| *active != modifier);
+        } else {
+            self.0.push(modifier);
+        }
+    }
+
+    /**
+     * What the final score is worth with every modifier of the run, in
+     * percent. Worked out in whole numbers so replays of a run agree on it.
+     */
+    pub fn score_percent(&self) -> usize {
+        self.0.iter().fold(100, |percent, modifier| {
+            percent * modifier.score_percent() / 100
+        })
+    }
+
+    pub fn final_score(&self, score: usize) -> usize {
+        score * self.score_percent() / 100
+    }
+
+    // the batteries of a stage as the modifiers leave them
+    pub fn batteries(&self, batteries: Vec<BatteryLayout>) -> Vec<BatteryLayout> {
+        if !self.is_active(Modifier::HalfAmmo) {
+            return batteries;
+        }
+
+        batteries
+            .into_iter()
+            .map(|battery| BatteryLayout {
+                ammo: (battery.ammo / 2).max(1),
+                ..battery
+            })
+            .collect()
+    }
+
+    pub fn ufo_speed_scale(&self) -> f32 {
+        if self.is_active(Modifier::DoubleSpeed) {
+            DOUBLE_SPEED_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    pub fn enemy_speed_scale(&self) -> f32 {
+        let scale = self.ufo_speed_scale();
+        if self.is_active(Modifier::FastMissiles) {
+            scale * FAST_MISSILES_SCALE
+        } else {
+            scale
+        }
+    }
+
+    // how much wider the flames of a shot are
+    pub fn flame_scale(&self) -> f32 {
+        if self.is_active(Modifier::HugeExplosions) {
+            HUGE_EXPLOSIONS_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    // whether the city in this slot, counted from the left, is built
+    pub fn keeps_city(&self, slot: usize) -> bool {
+        !self.is_active(Modifier::OneCity) || slot == ONE_CITY_SLOT
+    }
+}
+
+// Enemies are spawned at the speed of the stage and sped up here
+pub fn speed_up_enemies(
+    mut missiles: Query<&mut Missile, (Added<Missile>, With<Enemy>)>,
+    mut flyers: Query<&mut Flyer, (Added<Flyer>, With<Enemy>)>,
+    modifiers: Res<RunModifiers>,
+) {
+    let scale = modifiers.enemy_speed_scale();
+    for mut missile in missiles.iter_mut() {
+        missile.vel *= scale;
+    }
+    let scale = modifiers.ufo_speed_scale();
+    for mut flyer in flyers.iter_mut() {
+        flyer.speed *= scale;
+    }
+}
@@ -0,0 +1,307 @@
+use bevy::{math::vec2, prelude::*};
+use bevy_prototype_lyon::{
+    prelude::{GeometryBuilder, ShapeBundle, Stroke},
+    shapes,
+};
+use bevy_turborand::{DelegatedRng, GlobalRng, RngComponent};
+use serde::Deserialize;
+
+use super::{
+    components::{
+        Battery, City, Destroyed, Enemy, ExplosionEvent, ExplosionMode, Flyer, FlyerDestroyedEvent,
+        Foreground, Health, Missile, MissileReserve, TankBody,
+    },
+    curve::Curve,
+    effects::{Flick, TimedRemoval},
+    prelude::{FlyerKind, Stage, StageHandle, Wave},
+};
+use crate::{ImageAssets, SCREEN};
+
+// How fast a power-up falls, and how long it lies on the ground before it is gone
+const FALL_SPEED: f32 = 30.0;
+const LINGER_SECS: f32 = 4.0;
+// How close a battery has to be to pick a power-up up
+const PICKUP_RADIUS: f32 = 20.0;
+const GROUND_Y: f32 = -SCREEN.y / 2.0 + 24.0;
+// How much wider the flames and faster the missiles of the player are while they last
+const BIG_BLAST_SCALE: f32 = 1.5;
+const FAST_MISSILES_SCALE: f32 = 1.5;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUpKind {
+    // wider flames for the shots of the player, for a while
+    BigBlast,
+    // faster missiles for the player, for a while
+    FastMissiles,
+    // every battery is loaded again
+    AmmoRefill,
+    // the most damaged city can't be hit, for a while
+    Shield,
+    // every enemy on screen blows up
+    ScreenClear,
+}
+
+impl PowerUpKind {
+    fn sprite(&self) -> usize {
+        match self {
+            PowerUpKind::BigBlast => 16,
+            PowerUpKind::FastMissiles => 17,
+            PowerUpKind::AmmoRefill => 18,
+            PowerUpKind::Shield => 19,
+            PowerUpKind::ScreenClear => 20,
+        }
+    }
+}
+
+/**
+ * A power-up a stage drops from its ufos, e.g.
+ * `{ "kind": "shield", "chance": 0.2, "duration_secs": 15.0 }`
+ */
+pub struct PowerUpSpec {
+    pub kind: PowerUpKind,
+    pub(super) chance: Curve,
+    pub duration_secs: f32,
+}
+
+impl PowerUpSpec {
+    pub fn chance(&self, wave: usize) -> f64 {
+        (self.chance.at(wave) as f64).clamp(0.0, 1.0)
+    }
+}
+
+// A power-up on its way to the ground
+#[derive(Component)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub duration_secs: f32,
+}
+
+// A city that can't be hit until the timer finishes
+#[derive(Component)]
+pub struct Shield(pub Timer);
+
+/**
+ * The power-ups picked up that last for a while, and how long they have left
+ */
+#[derive(Resource, Default)]
+pub struct ActivePowerUps(pub Vec<(PowerUpKind, Timer)>);
+
+impl ActivePowerUps {
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.0.iter().any(|(active, _)| *active == kind)
+    }
+
+    // picking one up again starts it over
+    fn start(&mut self, kind: PowerUpKind, secs: f32) {
+        self.0.retain(|(active, _)| *active != kind);
+        self.0
+            .push((kind, Timer::from_seconds(secs, TimerMode::Once)));
+    }
+
+    // how much faster the missiles of the player are
+    pub fn missile_scale(&self) -> f32 {
+        if self.is_active(PowerUpKind::FastMissiles) {
+            FAST_MISSILES_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    // how much wider the flames of a shot are
+    pub fn blast_scale(&self) -> f32 {
+        if self.is_active(PowerUpKind::BigBlast) {
+            BIG_BLAST_SCALE
+        } else {
+            1.0
+        }
+    }
+}
+
+/**
+ * A destroyed ufo may leave one of the power-ups of the stage behind, the
+ * first of them in the order of the stage file to win its roll
+ */
+pub fn drop_power_ups(
+    mut commands: Commands,
+    mut flyer_destruction: EventReader<FlyerDestroyedEvent>,
+    images: Res<ImageAssets>,
+    mut global_rng: ResMut<GlobalRng>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    wave: Res<Wave>,
+) {
+    let Some(stage) = stages.get(&stage.0) else {
+        return;
+    };
+    for destroyed in flyer_destruction.iter() {
+        // stages without power-ups draw nothing, so their runs play out as before
+        if destroyed.kind != FlyerKind::Ufo || stage.power_ups.is_empty() {
+            continue;
+        }
+
+        let mut rng = RngComponent::from(&mut global_rng);
+        let dropped = stage.power_ups.iter().find(|power_up| {
+            let chance = power_up.chance(wave.n);
+            chance > 0.0 && rng.chance(chance)
+        });
+        if let Some(power_up) = dropped {
+            commands.spawn((
+                SpriteSheetBundle {
+                    texture_atlas: images.cursor.clone(),
+                    sprite: TextureAtlasSprite::new(power_up.kind.sprite()),
+                    transform: Transform::from_translation(destroyed.pos),
+                    ..default()
+                },
+                PowerUp {
+                    kind: power_up.kind,
+                    duration_secs: power_up.duration_secs,
+                },
+                Foreground,
+            ));
+        }
+    }
+}
+
+/**
+ * Power-ups fall to the ground and flash for a while before they are gone
+ */
+pub fn fall_power_ups(
+    mut commands: Commands,
+    mut power_ups: Query<(Entity, &mut Transform), (With<PowerUp>, Without<TimedRemoval>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform) in power_ups.iter_mut() {
+        transform.translation.y -= FALL_SPEED * time.delta_seconds();
+        if transform.translation.y > GROUND_Y {
+            continue;
+        }
+
+        transform.translation.y = GROUND_Y;
+        commands.entity(entity).insert((
+            TimedRemoval(Timer::from_seconds(LINGER_SECS, TimerMode::Once)),
+            Flick {
+                duration: Timer::from_seconds(LINGER_SECS, TimerMode::Once),
+                switch_timer: Timer::from_seconds(0.2, TimerMode::Repeating),
+            },
+        ));
+    }
+}
+
+/**
+ * An intact battery picks up the power-ups that come close, falling or on
+ * the ground
+ */
+pub fn pick_up_power_ups(
+    mut commands: Commands,
+    power_ups: Query<(Entity, &Transform, &PowerUp)>,
+    mut batteries: Query<(&Transform, &TankBody, &Battery, &mut MissileReserve)>,
+    cities: Query<(Entity, &Transform, &Health), (With<City>, Without<Destroyed>, Without<Shield>)>,
+    enemies: Query<&Transform, (With<Enemy>, Or<(With<Missile>, With<Flyer>)>)>,
+    mut active: ResMut<ActivePowerUps>,
+    mut explosion_event: EventWriter<ExplosionEvent>,
+) {
+    for (entity, transform, power_up) in power_ups.iter() {
+        let pos = transform.translation.truncate();
+        let picked = batteries.iter().any(|(tank_transform, tank, _, _)| {
+            matches!(tank, TankBody::Intact)
+                && tank_transform.translation.truncate().distance(pos) < PICKUP_RADIUS
+        });
+        if !picked {
+            continue;
+        }
+
+        commands.entity(entity).despawn();
+        match power_up.kind {
+            PowerUpKind::BigBlast | PowerUpKind::FastMissiles => {
+                active.start(power_up.kind, power_up.duration_secs)
+            }
+            PowerUpKind::AmmoRefill => {
+                for (_, tank, battery, mut ammo) in batteries.iter_mut() {
+                    if matches!(tank, TankBody::Intact) {
+                        ammo.0 = battery.ammo;
+                    }
+                }
+            }
+            PowerUpKind::Shield => {
+                // the most damaged city, the closest one of those
+                let shielded = cities.iter().min_by(|(_, a, a_health), (_, b, b_health)| {
+                    a_health.current.cmp(&b_health.current).then(
+                        (a.translation.x - pos.x)
+                            .abs()
+                            .total_cmp(&(b.translation.x - pos.x).abs()),
+                    )
+                });
+                if let Some((city, _, _)) = shielded {
+                    shield_city(&mut commands, city, power_up.duration_secs);
+                }
+            }
+            PowerUpKind::ScreenClear => {
+                for enemy in enemies.iter() {
+                    explosion_event.send(ExplosionEvent {
+                        pos: enemy.translation,
+                        mode: ExplosionMode::Single,
+                        shot: false,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn shield_city(commands: &mut Commands, city: Entity, secs: f32) {
+    let bubble = commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shapes::Circle {
+                    radius: 20.0,
+                    center: vec2(0.0, 0.0),
+                }),
+                transform: Transform::from_xyz(0.0, 0.0, 0.5),
+                ..default()
+            },
+            Stroke::new(Color::WHITE, 1.0),
+        ))
+        .id();
+    commands
+        .entity(city)
+        .insert(Shield(Timer::from_seconds(secs, TimerMode::Once)))
+        .add_child(bubble);
+}
+
+/**
+ * Runs out the power-ups that last for a while and the shields over cities
+ */
+pub fn tick_power_ups(
+    mut commands: Commands,
+    mut active: ResMut<ActivePowerUps>,
+    mut shields: Query<(Entity, &mut Shield)>,
+    time: Res<Time>,
+) {
+    for (_, timer) in active.0.iter_mut() {
+        timer.tick(time.delta());
+    }
+    active.0.retain(|(_, timer)| !timer.finished());
+
+    for (city, mut shield) in shields.iter_mut() {
+        shield.0.tick(time.delta());
+        if shield.0.finished() {
+            commands
+                .entity(city)
+                .remove::<Shield>()
+                .despawn_descendants();
+        }
+    }
+}
+
+// nothing picked up lasts past the stage it was picked up on
+pub fn teardown_power_ups(
+    mut commands: Commands,
+    power_ups: Query<Entity, With<PowerUp>>,
+    mut active: ResMut<ActivePowerUps>,
+) {
+    for entity in power_ups.iter() {
+        commands.entity(entity).despawn();
+    }
+    active.0.clear();
+}
@@ -3,83 +3,206 @@ use bevy::{
     prelude::*,
     reflect::{TypePath, TypeUuid},
 };
+use serde::Deserialize;
 
-use super::components::Foreground;
+pub use super::{
+    bonus::BonusTally,
+    components::{
+        ActiveBattery, Battery, City, Destroyed, Enemy, FlameRadius, Flyer, Missile,
+        MissileReserve, Player, Score, Stepper, TankBody, FLAME_RADIUS_STEPS, FLAME_STEP_SECS,
+        MAX_AMMO, PLAYER_MISSILE_SPEED,
+    },
+    input::{InputSet, InputSource, PlayerInput},
+    modifiers::{Modifier, RunModifiers},
+    power_ups::ActivePowerUps,
+    script::ScriptPlayback,
+    tick::{GameTick, TickSet, TICK},
+    upgrades::{flame_steps, shot_flame_scale, RunUpgrades, Upgrade, UpgradeList},
+};
+use super::{
+    components::{Foreground, Scoring},
+    curve::Curve,
+    power_ups::PowerUpSpec,
+    script::WaveScript,
+};
 
-#[derive(serde::Deserialize, TypeUuid, TypePath)]
+/**
+ * A stage as played. Loaded from a `*.stage.json` file by the `StageLoader`,
+ * where every difficulty parameter is a curve evaluated per wave.
+ */
+#[derive(TypeUuid, TypePath)]
 #[uuid = "413be529-bfeb-41b3-9db0-4b8b380a2c46"]
 pub struct Stage {
     pub name: String,
     pub bread: String,
-    spawn_interval_secs: f32,
-    split_interval_secs: f32,
-    enemies_count: usize,
-    missile_spawn_min: usize,
-    missile_spawn_max: usize,
-    missile_speed: f32,
-    ufo_speed: f32,
-    drop_bomb_chance: f64,
-    ufo_chance: f64,
-    split_chance: f64,
-    max_split: u8,
-    difficulty_base: f32,
-    difficulty_rate: f32,
+    pub(super) spawn_interval_secs: Curve,
+    pub(super) split_interval_secs: Curve,
+    pub(super) enemies_count: Curve,
+    pub(super) missile_spawn_min: Curve,
+    pub(super) missile_spawn_max: Curve,
+    pub(super) missile_speed: Curve,
+    pub(super) ufo_speed: Curve,
+    pub(super) drop_bomb_chance: Curve,
+    pub(super) ufo_chance: Curve,
+    pub(super) ufo_turn_chance: Curve,
+    pub(super) split_chance: Curve,
+    pub(super) max_split: Curve,
+    pub(super) smart_bomb_chance: Curve,
+    pub(super) mirv_chance: Curve,
+    pub(super) mirv_warheads: Curve,
+    pub targeting: TargetWeights,
+    pub(super) target_scatter: Curve,
+    // height above the ground where every mirv splits
+    pub mirv_split_altitude: f32,
+    pub(super) bonus_multiplier: Curve,
+    pub(super) scripts: Vec<WaveScript>,
+    pub batteries: Vec<BatteryLayout>,
+    // flying enemies besides the ufo
+    pub(super) flyers: Vec<FlyerSpec>,
+    // what a destroyed ufo may drop
+    pub(super) power_ups: Vec<PowerUpSpec>,
+    // what changes every `MILESTONE_WAVES` waves, in order
+    pub(super) milestones: Vec<Milestone>,
+    // points for every city left standing and every missile left unfired at the end of a wave
+    pub city_bonus: usize,
+    pub ammo_bonus: usize,
+    pub waves: Option<usize>,
+    pub goal_score: Option<usize>,
+    // makes the stage play out the same every time, regardless of the run seed
+    pub seed: Option<u64>,
     pub text_cor: Vec<u8>,
     pub bg_cor: Vec<u8>,
     pub fg_cor: Vec<u8>,
     pub trail_cor: Vec<u8>,
+    // filled in by the loader, a stage with problems can't be played
+    pub problems: Vec<String>,
 }
 
 impl Stage {
     pub fn spawn_interval_secs(&self, wave: usize) -> f32 {
-        f32::max(1.75, self.spawn_interval_secs - (wave as f32 / 10.0))
+        self.spawn_interval_secs.at(wave)
     }
     pub fn split_interval_secs(&self, wave: usize) -> f32 {
-        f32::max(
-            1.5,
-            self.spawn_interval_secs / (self.difficulty_base + wave as f32 * self.difficulty_rate),
-        )
+        self.split_interval_secs.at(wave)
     }
     pub fn enemies_count(&self, wave: usize) -> usize {
-        self.enemies_count + (wave * 2)
+        self.enemies_count.at(wave) as usize
     }
     pub fn missile_spawn_min(&self, wave: usize) -> usize {
-        (self.missile_spawn_min as f32 * (wave as f32 * self.difficulty_rate)) as usize
+        self.missile_spawn_min.at(wave) as usize
     }
 
     pub fn missile_spawn_max(&self, wave: usize) -> usize {
-        self.missile_spawn_max + wave / 10
+        self.missile_spawn_max.at(wave) as usize
     }
 
     pub fn ufo_speed(&self, wave: usize) -> f32 {
-        f32::min(
-            50.0,
-            self.ufo_speed + wave as f32 * self.difficulty_base * 3.3,
-        )
+        self.ufo_speed.at(wave)
     }
     pub fn missile_speed(&self, wave: usize) -> f32 {
-        f32::min(50.0, self.missile_speed + wave as f32 * 4.5)
+        self.missile_speed.at(wave)
     }
 
     pub fn drop_bomb_chance(&self, wave: usize) -> f64 {
-        self.drop_bomb_chance + ((wave as f32 / 10.0) * self.difficulty_rate) as f64
+        chance(&self.drop_bomb_chance, wave)
     }
 
     pub fn ufo_chance(&self, wave: usize) -> f64 {
-        f64::min(
-            1.0,
-            self.ufo_chance + ((wave as f32 / 10.0) * self.difficulty_rate) as f64,
-        )
+        chance(&self.ufo_chance, wave)
     }
 
-    // increases by 1% per wave
     pub fn split_chance(&self, wave: usize) -> f64 {
-        f64::min(1.0, self.split_chance + 0.01 * wave as f64)
+        chance(&self.split_chance, wave)
     }
 
-    // increases by 1 per 10 waves
     pub fn max_split(&self, wave: usize) -> u8 {
-        self.max_split + (wave / 10) as u8
+        self.max_split.at(wave) as u8
+    }
+
+    // the chance of a missile spawned by the random spawner being a smart bomb
+    pub fn smart_bomb_chance(&self, wave: usize) -> f64 {
+        let extra = self
+            .milestone(wave)
+            .map_or(0.0, |milestone| milestone.smart_bomb_chance);
+        (chance(&self.smart_bomb_chance, wave) + extra).min(1.0)
+    }
+
+    // the chance of a missile spawned by the random spawner being a mirv
+    pub fn mirv_chance(&self, wave: usize) -> f64 {
+        let extra = self
+            .milestone(wave)
+            .map_or(0.0, |milestone| milestone.mirv_chance);
+        (chance(&self.mirv_chance, wave) + extra).min(1.0)
+    }
+
+    pub fn mirv_warheads(&self, wave: usize) -> u8 {
+        self.mirv_warheads.at(wave) as u8
+    }
+
+    // how far from its target a missile may land
+    pub fn target_scatter(&self, wave: usize) -> f32 {
+        self.target_scatter.at(wave).max(0.0)
+    }
+
+    pub fn bonus_multiplier(&self, wave: usize) -> usize {
+        self.bonus_multiplier.at(wave).max(0.0) as usize
+    }
+
+    /**
+     * Every kind of flying enemy of the stage as it is on a wave, the ufo
+     * first so stages without other flyers draw the same random numbers
+     */
+    pub fn flyers(&self, wave: usize) -> Vec<FlyerParams> {
+        let ufo = FlyerParams {
+            kind: FlyerKind::Ufo,
+            chance: self.ufo_chance(wave),
+            speed: self.ufo_speed(wave),
+            score: Scoring::Ufo as usize,
+            bombs: 1,
+            bomb_interval_secs: 1.0,
+            bomb_chance: self.drop_bomb_chance(wave),
+            altitude: UFO_ALTITUDE,
+            arc_height: 0.0,
+            turn_chance: chance(&self.ufo_turn_chance, wave),
+        };
+        let milestone_flyers = self
+            .milestone(wave)
+            .map_or(&[][..], |milestone| &milestone.flyers[..]);
+        std::iter::once(ufo)
+            .chain(self.flyers.iter().map(|flyer| flyer.at(wave)))
+            .chain(milestone_flyers.iter().map(|flyer| flyer.at(wave)))
+            .collect()
+    }
+
+    /**
+     * The milestone the run is at on a wave, none before the first
+     * `MILESTONE_WAVES` waves are played. The list starts over after the last.
+     */
+    pub fn milestone(&self, wave: usize) -> Option<&Milestone> {
+        let reached = wave / MILESTONE_WAVES;
+        (reached > 0 && !self.milestones.is_empty())
+            .then(|| &self.milestones[(reached - 1) % self.milestones.len()])
+    }
+
+    // the colors of a wave, the milestone's where it has its own
+    pub fn palette(&self, wave: usize) -> Palette<'_> {
+        let milestone = self.milestone(wave);
+        Palette {
+            bg: milestone
+                .and_then(|milestone| milestone.bg_cor.as_deref())
+                .unwrap_or(&self.bg_cor),
+            fg: milestone
+                .and_then(|milestone| milestone.fg_cor.as_deref())
+                .unwrap_or(&self.fg_cor),
+            trail: milestone
+                .and_then(|milestone| milestone.trail_cor.as_deref())
+                .unwrap_or(&self.trail_cor),
+        }
+    }
+
+    // the script for a wave of this stage, counted from the first wave of the stage
+    pub fn script(&self, stage_wave: usize) -> Option<&WaveScript> {
+        self.scripts.iter().find(|script| script.wave == stage_wave)
     }
 
     // 1. Fortsett med å konvertere til fns og start å bruke dem i game
@@ -88,30 +211,261 @@ impl Stage {
     // 3. lag overgang fra en wave til neste. Vise poeng kalkulering?
 }
 
+// Every this many waves a stage with milestones moves on to the next one
+pub const MILESTONE_WAVES: usize = 10;
+
+/**
+ * A new palette and enemy mix every `MILESTONE_WAVES` waves, e.g.
+ * `{ "name": "Smart bombs", "bg_cor": [20, 40, 90], "smart_bomb_chance": 0.1 }`
+ */
+pub struct Milestone {
+    pub name: String,
+    // the colors from here on, the stage's own where left out
+    pub bg_cor: Option<Vec<u8>>,
+    pub fg_cor: Option<Vec<u8>>,
+    pub trail_cor: Option<Vec<u8>>,
+    // added to the chances of the stage
+    pub smart_bomb_chance: f64,
+    pub mirv_chance: f64,
+    // flown in on top of the flyers of the stage
+    pub(super) flyers: Vec<FlyerSpec>,
+}
+
+// The colors a wave is drawn with
+pub struct Palette<'a> {
+    pub bg: &'a [u8],
+    pub fg: &'a [u8],
+    pub trail: &'a [u8],
+}
+
+// The heights above the ground a ufo has always flown in at
+pub const UFO_ALTITUDE: [f32; 2] = [119.0, 224.0];
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FlyerKind {
+    // flies straight across and may turn back
+    Ufo,
+    // flies straight across, dropping a stick of bombs
+    Bomber,
+    // flies across over a hump
+    Satellite,
+}
+
+/**
+ * A kind of flying enemy as given in the stage file, e.g.
+ * `{ "kind": "bomber", "chance": 0.1, "bombs": 3 }`
+ */
+pub struct FlyerSpec {
+    pub kind: FlyerKind,
+    pub(super) chance: Curve,
+    pub(super) speed: Curve,
+    pub(super) bomb_chance: Curve,
+    pub(super) turn_chance: Curve,
+    pub score: usize,
+    pub bombs: u8,
+    pub bomb_interval_secs: f32,
+    // the lowest and highest height above the ground it flies in at
+    pub altitude: [f32; 2],
+    pub arc_height: f32,
+}
+
+impl FlyerSpec {
+    pub fn at(&self, wave: usize) -> FlyerParams {
+        FlyerParams {
+            kind: self.kind,
+            chance: chance(&self.chance, wave),
+            speed: self.speed.at(wave),
+            score: self.score,
+            bombs: self.bombs,
+            bomb_interval_secs: self.bomb_interval_secs,
+            bomb_chance: chance(&self.bomb_chance, wave),
+            altitude: self.altitude,
+            arc_height: self.arc_height,
+            turn_chance: chance(&self.turn_chance, wave),
+        }
+    }
+}
+
+// A kind of flying enemy on a given wave
+#[derive(Clone, Copy, Debug)]
+pub struct FlyerParams {
+    pub kind: FlyerKind,
+    // the chance of one being spawned every spawn tick
+    pub chance: f64,
+    pub speed: f32,
+    pub score: usize,
+    pub bombs: u8,
+    pub bomb_interval_secs: f32,
+    pub bomb_chance: f64,
+    pub altitude: [f32; 2],
+    pub arc_height: f32,
+    pub turn_chance: f64,
+}
+
+// Where a missile battery stands and what it holds
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BatteryLayout {
+    pub x: f32,
+    pub ammo: u8,
+    // the least time between two shots of the battery
+    pub cooldown_secs: f32,
+}
+
+impl Default for BatteryLayout {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            ammo: MAX_AMMO,
+            cooldown_secs: 0.0,
+        }
+    }
+}
+
+/**
+ * How often a randomly spawned missile aims at each kind of target, e.g.
+ * `{ "cities": 3.0, "batteries": 1.0, "ground": 1.0 }`
+ */
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TargetWeights {
+    pub cities: f32,
+    pub batteries: f32,
+    pub ground: f32,
+}
+
+// anywhere on the ground, as missiles have always landed
+impl Default for TargetWeights {
+    fn default() -> Self {
+        Self {
+            cities: 0.0,
+            batteries: 0.0,
+            ground: 1.0,
+        }
+    }
+}
+
+// a curve may run past what a chance can be at high waves
+fn chance(curve: &Curve, wave: usize) -> f64 {
+    (curve.at(wave) as f64).clamp(0.0, 1.0)
+}
+
 #[derive(Resource)]
 pub struct Wave {
     pub n: usize,
+    // the wave the current stage started at
+    pub first: usize,
     pub completion_timeout: Timer,
 }
 
+impl Wave {
+    // the wave counted from the start of the current stage
+    pub fn stage_wave(&self) -> usize {
+        self.n - self.first
+    }
+}
+
+#[derive(Event)]
+pub struct WaveCompletedEvent {
+    pub wave: usize,
+    pub stats: WaveStats,
+}
+
+// The run has reached the next milestone of the stage, from the wave on
+#[derive(Event)]
+pub struct MilestoneEvent {
+    pub wave: usize,
+    pub name: String,
+}
+
+// What happened during the current wave, reset when the wave is completed
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct WaveStats {
+    pub missiles_spawned: usize,
+    pub missiles_destroyed: usize,
+    pub ufos_spawned: usize,
+    pub ufos_destroyed: usize,
+    pub shots_fired: usize,
+    // shots whose explosion took at least one enemy with it
+    pub shots_hit: usize,
+    pub largest_combo: usize,
+    pub cities_lost: usize,
+}
+
+impl WaveStats {
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.shots_hit as f32 / self.shots_fired as f32)
+    }
+}
+
 #[derive(Resource)]
 pub struct StageHandle(pub Handle<Stage>);
 
+// Every random draw of a run follows from this seed
+#[derive(Resource)]
+pub struct RunSeed(pub u64);
+// Every run of the session is played from the same seed (`--seed`, replays)
+#[derive(Resource)]
+pub struct FixedSeed;
+
+pub fn random_seed() -> u64 {
+    let mut bytes = [0; 8];
+    getrandom::getrandom(&mut bytes).expect("no source of randomness for the seed");
+    u64::from_le_bytes(bytes)
+}
+
+// Whether the game is paused, alongside `GameState` so pausing doesn't tear
+// the stage down
+#[derive(States, Hash, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    // the stages are played in order, each until it is cleared
+    #[default]
+    Campaign,
+    // a single stage played until the player is overrun
+    Endless,
+}
+
+/**
+ * Forgets the run in progress, the next stage entered starts a new run with
+ * no score from wave 1
+ */
+pub fn reset_run(commands: &mut Commands) {
+    commands.add(|world: &mut World| {
+        world.remove_resource::<Wave>();
+        world.remove_resource::<BonusTally>();
+        world.insert_resource(RunUpgrades::default());
+        world.insert_resource(Score(0));
+        world.insert_resource(PlayerInput::default());
+        if !world.contains_resource::<FixedSeed>() {
+            world.insert_resource(RunSeed(random_seed()));
+        }
+    });
+}
+
 pub fn stage_colors(
     mut foregrounds: Query<(&mut Sprite), (With<Foreground>)>,
     mut cameras: Query<(&mut Camera2d)>,
     stage: Res<StageHandle>,
     stages: Res<Assets<Stage>>,
+    wave: Option<Res<Wave>>,
 ) {
     let stage = stages.get(&stage.0);
     if let Some(stage) = stage {
+        let palette = stage.palette(wave.map_or(0, |wave| wave.n));
         for mut sprite in foregrounds.iter_mut() {
             // why is this NOT WORKING?!
-            sprite.color = Color::from(color_from_vec(&stage.fg_cor));
+            sprite.color = Color::from(color_from_vec(palette.fg));
         }
         for mut camera in cameras.iter_mut() {
-            camera.clear_color =
-                ClearColorConfig::Custom(Color::from(color_from_vec(&stage.bg_cor)));
+            camera.clear_color = ClearColorConfig::Custom(Color::from(color_from_vec(palette.bg)));
         }
     }
 }
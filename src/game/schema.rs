@@ -0,0 +1,986 @@
+use bevy::{
+    asset::{AssetLoader, Error, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{de, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+use super::{
+    curve::{Curve, Shape},
+    power_ups::{PowerUpKind, PowerUpSpec},
+    prelude::{BatteryLayout, FlyerKind, FlyerSpec, Milestone, Stage, TargetWeights, UFO_ALTITUDE},
+    script::{Spawn, WaveScript},
+};
+use crate::SCREEN;
+
+/**
+ * The current layout of stage files.
+ *
+ * 0: the first prototype, `spawn_rate` instead of `spawn_interval_secs`
+ * 1: unversioned files with the full set of difficulty fields
+ * 2: `version` field, difficulty parameters can be curves
+ */
+pub const STAGE_VERSION: u64 = 2;
+pub const MAX_BATTERIES: usize = 9;
+
+#[derive(Default)]
+pub struct StageLoader;
+
+impl AssetLoader for StageLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let path = load_context.path().display().to_string();
+            let stage = read_stage(bytes, &path);
+            for problem in stage.problems.iter() {
+                error!("{path}: {problem}");
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(stage));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["stage.json"]
+    }
+}
+
+// A broken stage is still read so the problems can be shown in game
+fn read_stage(bytes: &[u8], path: &str) -> Stage {
+    parse_stage(bytes).unwrap_or_else(|problem| Stage {
+        name: path.to_string(),
+        problems: vec![problem],
+        ..default()
+    })
+}
+
+pub fn parse_stage(bytes: &[u8]) -> Result<Stage, String> {
+    let value: Value =
+        serde_json::from_slice(bytes).map_err(|err| format!("not a valid JSON file: {err}"))?;
+    let value = migrate(value)?;
+    // the path of the field that is wrong, e.g. `flyers[0].speed`
+    let file: StageFile = serde_path_to_error::deserialize(value)
+        .map_err(|err| format!("invalid stage: {}: {}", err.path(), err.inner()))?;
+    let mut stage = Stage::from(file);
+    stage.problems = validate(&stage);
+
+    Ok(stage)
+}
+
+/**
+ * Brings a stage file of any known version up to `STAGE_VERSION`
+ */
+fn migrate(mut value: Value) -> Result<Value, String> {
+    let fields = value
+        .as_object_mut()
+        .ok_or("a stage file must be a JSON object")?;
+
+    let mut version = match fields.get("version") {
+        Some(version) => version.as_u64().ok_or("version must be a whole number")?,
+        None if fields.contains_key("spawn_rate") => 0,
+        None => 1,
+    };
+    if version > STAGE_VERSION {
+        return Err(format!(
+            "version {version} is newer than this game supports ({STAGE_VERSION})"
+        ));
+    }
+
+    while version < STAGE_VERSION {
+        if version == 0 {
+            rename(fields, "spawn_rate", "spawn_interval_secs");
+        }
+        version += 1;
+    }
+    fields.insert("version".into(), version.into());
+
+    Ok(value)
+}
+
+fn rename(fields: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(value) = fields.remove(from) {
+        fields.insert(to.into(), value);
+    }
+}
+
+/**
+ * A difficulty parameter as written in the stage file. A plain number is
+ * scaled per wave by the same formula every stage used before curves.
+ */
+#[derive(Clone)]
+enum Tunable {
+    Base(f32),
+    Curve(Curve),
+}
+
+// Says what a curve got wrong, rather than that the value is neither a number nor a curve
+impl<'de> Deserialize<'de> for Tunable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        if let Some(base) = value.as_f64() {
+            return Ok(Tunable::Base(base as f32));
+        }
+
+        Curve::deserialize(value)
+            .map(Tunable::Curve)
+            .map_err(|err| de::Error::custom(format!("expected a number or a curve, {err}")))
+    }
+}
+
+impl Tunable {
+    fn base(&self) -> f32 {
+        match self {
+            Tunable::Base(base) => *base,
+            Tunable::Curve(curve) => curve.at(0),
+        }
+    }
+
+    fn or_else(self, default: impl FnOnce(f32) -> Curve) -> Curve {
+        match self {
+            Tunable::Base(base) => default(base),
+            Tunable::Curve(curve) => curve,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct StageFile {
+    name: String,
+    bread: String,
+    spawn_interval_secs: Tunable,
+    split_interval_secs: Tunable,
+    enemies_count: Tunable,
+    missile_spawn_min: Tunable,
+    missile_spawn_max: Tunable,
+    missile_speed: Tunable,
+    ufo_speed: Tunable,
+    drop_bomb_chance: Tunable,
+    ufo_chance: Tunable,
+    ufo_turn_chance: Tunable,
+    split_chance: Tunable,
+    max_split: Tunable,
+    smart_bomb_chance: Tunable,
+    mirv_chance: Tunable,
+    mirv_warheads: Tunable,
+    targeting: TargetWeights,
+    target_scatter: Tunable,
+    mirv_split_altitude: f32,
+    bonus_multiplier: Tunable,
+    scripts: Vec<WaveScript>,
+    batteries: Vec<BatteryLayout>,
+    flyers: Vec<FlyerFile>,
+    power_ups: Vec<PowerUpFile>,
+    milestones: Vec<MilestoneFile>,
+    city_bonus: usize,
+    ammo_bonus: usize,
+    difficulty_base: f32,
+    difficulty_rate: f32,
+    waves: Option<usize>,
+    goal_score: Option<usize>,
+    seed: Option<u64>,
+    text_cor: Vec<u8>,
+    bg_cor: Vec<u8>,
+    fg_cor: Vec<u8>,
+    trail_cor: Vec<u8>,
+}
+
+impl Default for StageFile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            bread: String::new(),
+            spawn_interval_secs: Tunable::Base(2.7),
+            split_interval_secs: Tunable::Base(3.0),
+            enemies_count: Tunable::Base(12.0),
+            missile_spawn_min: Tunable::Base(0.0),
+            missile_spawn_max: Tunable::Base(1.0),
+            missile_speed: Tunable::Base(20.0),
+            ufo_speed: Tunable::Base(25.0),
+            drop_bomb_chance: Tunable::Base(0.1),
+            ufo_chance: Tunable::Base(0.1),
+            ufo_turn_chance: Tunable::Base(0.0),
+            split_chance: Tunable::Base(0.05),
+            max_split: Tunable::Base(2.0),
+            smart_bomb_chance: Tunable::Base(0.0),
+            mirv_chance: Tunable::Base(0.0),
+            mirv_warheads: Tunable::Base(3.0),
+            targeting: TargetWeights::default(),
+            target_scatter: Tunable::Base(32.0),
+            mirv_split_altitude: 120.0,
+            bonus_multiplier: Tunable::Base(1.0),
+            scripts: Vec::new(),
+            batteries: vec![BatteryLayout::default()],
+            flyers: Vec::new(),
+            power_ups: Vec::new(),
+            milestones: Vec::new(),
+            city_bonus: 100,
+            ammo_bonus: 5,
+            difficulty_base: 1.0,
+            difficulty_rate: 0.1,
+            waves: None,
+            goal_score: None,
+            seed: None,
+            text_cor: vec![255, 231, 155],
+            bg_cor: vec![64, 18, 139],
+            fg_cor: vec![221, 88, 214],
+            trail_cor: vec![147, 54, 180],
+        }
+    }
+}
+
+/**
+ * A flying enemy as written in the stage file. What is left out falls back
+ * to the defaults of its kind, and plain numbers stay the same every wave.
+ */
+#[derive(Deserialize)]
+struct FlyerFile {
+    kind: FlyerKind,
+    chance: Option<Tunable>,
+    speed: Option<Tunable>,
+    bomb_chance: Option<Tunable>,
+    turn_chance: Option<Tunable>,
+    score: Option<usize>,
+    bombs: Option<u8>,
+    bomb_interval_secs: Option<f32>,
+    altitude: Option<[f32; 2]>,
+    arc_height: Option<f32>,
+}
+
+impl FlyerSpec {
+    pub fn default_for(kind: FlyerKind) -> Self {
+        FlyerFile {
+            kind,
+            chance: None,
+            speed: None,
+            bomb_chance: None,
+            turn_chance: None,
+            score: None,
+            bombs: None,
+            bomb_interval_secs: None,
+            altitude: None,
+            arc_height: None,
+        }
+        .into()
+    }
+}
+
+impl From<FlyerFile> for FlyerSpec {
+    fn from(file: FlyerFile) -> Self {
+        // chance, speed, score, bombs, bomb interval, bomb chance, altitude, arc height
+        let (chance, speed, score, bombs, interval, bomb_chance, altitude, arc_height) =
+            match file.kind {
+                FlyerKind::Ufo => (0.1, 25.0, 1000, 1, 1.0, 0.1, UFO_ALTITUDE, 0.0),
+                FlyerKind::Bomber => (0.05, 30.0, 500, 3, 0.6, 1.0, [60.0, 120.0], 0.0),
+                FlyerKind::Satellite => (0.05, 40.0, 800, 2, 1.5, 0.5, [140.0, 190.0], 40.0),
+            };
+        let constant = |tunable: Option<Tunable>, default: f32| {
+            tunable
+                .unwrap_or(Tunable::Base(default))
+                .or_else(|value| Curve::new(Shape::Constant(value)))
+        };
+
+        FlyerSpec {
+            kind: file.kind,
+            chance: constant(file.chance, chance),
+            speed: constant(file.speed, speed),
+            bomb_chance: constant(file.bomb_chance, bomb_chance),
+            turn_chance: constant(file.turn_chance, 0.0),
+            score: file.score.unwrap_or(score),
+            bombs: file.bombs.unwrap_or(bombs),
+            bomb_interval_secs: file.bomb_interval_secs.unwrap_or(interval),
+            altitude: file.altitude.unwrap_or(altitude),
+            arc_height: file.arc_height.unwrap_or(arc_height),
+        }
+    }
+}
+
+/**
+ * A power-up as written in the stage file, what is left out falls back to
+ * the defaults of its kind
+ */
+#[derive(Deserialize)]
+struct PowerUpFile {
+    kind: PowerUpKind,
+    chance: Option<Tunable>,
+    duration_secs: Option<f32>,
+}
+
+impl From<PowerUpFile> for PowerUpSpec {
+    fn from(file: PowerUpFile) -> Self {
+        // the ones picked up at once don't last
+        let duration_secs = match file.kind {
+            PowerUpKind::BigBlast | PowerUpKind::FastMissiles => 10.0,
+            PowerUpKind::Shield => 15.0,
+            PowerUpKind::AmmoRefill | PowerUpKind::ScreenClear => 0.0,
+        };
+
+        PowerUpSpec {
+            kind: file.kind,
+            chance: file
+                .chance
+                .unwrap_or(Tunable::Base(0.2))
+                .or_else(|chance| Curve::new(Shape::Constant(chance))),
+            duration_secs: file.duration_secs.unwrap_or(duration_secs),
+        }
+    }
+}
+
+/**
+ * A milestone as written in the stage file, everything but the name can be
+ * left out
+ */
+#[derive(Deserialize)]
+struct MilestoneFile {
+    name: String,
+    bg_cor: Option<Vec<u8>>,
+    fg_cor: Option<Vec<u8>>,
+    trail_cor: Option<Vec<u8>>,
+    #[serde(default)]
+    smart_bomb_chance: f64,
+    #[serde(default)]
+    mirv_chance: f64,
+    #[serde(default)]
+    flyers: Vec<FlyerFile>,
+}
+
+impl From<MilestoneFile> for Milestone {
+    fn from(file: MilestoneFile) -> Self {
+        Milestone {
+            name: file.name,
+            bg_cor: file.bg_cor,
+            fg_cor: file.fg_cor,
+            trail_cor: file.trail_cor,
+            smart_bomb_chance: file.smart_bomb_chance,
+            mirv_chance: file.mirv_chance,
+            flyers: file.flyers.into_iter().map(FlyerSpec::from).collect(),
+        }
+    }
+}
+
+impl From<StageFile> for Stage {
+    fn from(file: StageFile) -> Self {
+        let (base, rate) = (file.difficulty_base, file.difficulty_rate);
+        // the split interval has always been derived from the spawn interval
+        let spawn_interval = file.spawn_interval_secs.base();
+        let mut scripts = file.scripts;
+        for script in scripts.iter_mut() {
+            script
+                .events
+                .sort_by(|a, b| a.at.partial_cmp(&b.at).unwrap_or(std::cmp::Ordering::Equal));
+        }
+
+        Stage {
+            name: file.name,
+            bread: file.bread,
+            spawn_interval_secs: file.spawn_interval_secs.or_else(|secs| {
+                Curve::new(Shape::Linear {
+                    base: secs,
+                    rate: -0.1,
+                })
+                .with_min(1.75)
+            }),
+            split_interval_secs: file.split_interval_secs.or_else(|_| {
+                Curve::new(Shape::Inverse {
+                    scale: spawn_interval,
+                    base,
+                    rate,
+                })
+                .with_min(1.5)
+            }),
+            enemies_count: file.enemies_count.or_else(|count| {
+                Curve::new(Shape::Linear {
+                    base: count,
+                    rate: 2.0,
+                })
+            }),
+            missile_spawn_min: file.missile_spawn_min.or_else(|min| {
+                Curve::new(Shape::Linear {
+                    base: 0.0,
+                    rate: min * rate,
+                })
+            }),
+            missile_spawn_max: file.missile_spawn_max.or_else(|max| {
+                Curve::new(Shape::Step {
+                    base: max,
+                    rate: 1.0,
+                    every: 10,
+                })
+            }),
+            missile_speed: file.missile_speed.or_else(|speed| {
+                Curve::new(Shape::Linear {
+                    base: speed,
+                    rate: 4.5,
+                })
+                .with_max(50.0)
+            }),
+            ufo_speed: file.ufo_speed.or_else(|speed| {
+                Curve::new(Shape::Linear {
+                    base: speed,
+                    rate: base * 3.3,
+                })
+                .with_max(50.0)
+            }),
+            drop_bomb_chance: file.drop_bomb_chance.or_else(|chance| {
+                Curve::new(Shape::Linear {
+                    base: chance,
+                    rate: rate / 10.0,
+                })
+            }),
+            ufo_chance: file.ufo_chance.or_else(|chance| {
+                Curve::new(Shape::Linear {
+                    base: chance,
+                    rate: rate / 10.0,
+                })
+                .with_max(1.0)
+            }),
+            ufo_turn_chance: file
+                .ufo_turn_chance
+                .or_else(|chance| Curve::new(Shape::Constant(chance))),
+            split_chance: file.split_chance.or_else(|chance| {
+                Curve::new(Shape::Linear {
+                    base: chance,
+                    rate: 0.01,
+                })
+                .with_max(1.0)
+            }),
+            max_split: file.max_split.or_else(|max| {
+                Curve::new(Shape::Step {
+                    base: max,
+                    rate: 1.0,
+                    every: 10,
+                })
+            }),
+            smart_bomb_chance: file
+                .smart_bomb_chance
+                .or_else(|chance| Curve::new(Shape::Constant(chance))),
+            mirv_chance: file
+                .mirv_chance
+                .or_else(|chance| Curve::new(Shape::Constant(chance))),
+            mirv_warheads: file
+                .mirv_warheads
+                .or_else(|warheads| Curve::new(Shape::Constant(warheads))),
+            targeting: file.targeting,
+            // tighter every wave, but never dead on
+            target_scatter: file.target_scatter.or_else(|scatter| {
+                Curve::new(Shape::Linear {
+                    base: scatter,
+                    rate: -2.0,
+                })
+                .with_min(4.0)
+            }),
+            mirv_split_altitude: file.mirv_split_altitude,
+            // one more for every second wave, like the arcade
+            bonus_multiplier: file.bonus_multiplier.or_else(|multiplier| {
+                Curve::new(Shape::Step {
+                    base: multiplier,
+                    rate: 1.0,
+                    every: 2,
+                })
+                .with_max(6.0)
+            }),
+            scripts,
+            batteries: file.batteries,
+            flyers: file.flyers.into_iter().map(FlyerSpec::from).collect(),
+            power_ups: file.power_ups.into_iter().map(PowerUpSpec::from).collect(),
+            milestones: file.milestones.into_iter().map(Milestone::from).collect(),
+            city_bonus: file.city_bonus,
+            ammo_bonus: file.ammo_bonus,
+            waves: file.waves,
+            goal_score: file.goal_score,
+            seed: file.seed,
+            text_cor: file.text_cor,
+            bg_cor: file.bg_cor,
+            fg_cor: file.fg_cor,
+            trail_cor: file.trail_cor,
+            problems: Vec::new(),
+        }
+    }
+}
+
+impl Default for Stage {
+    fn default() -> Self {
+        StageFile::default().into()
+    }
+}
+
+/**
+ * Checks the curves themselves and what they evaluate to on the first wave
+ */
+fn validate(stage: &Stage) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if stage.name.trim().is_empty() {
+        problems.push("name is missing".to_string());
+    }
+    for (field, curve) in [
+        ("spawn_interval_secs", &stage.spawn_interval_secs),
+        ("split_interval_secs", &stage.split_interval_secs),
+        ("enemies_count", &stage.enemies_count),
+        ("missile_spawn_min", &stage.missile_spawn_min),
+        ("missile_spawn_max", &stage.missile_spawn_max),
+        ("missile_speed", &stage.missile_speed),
+        ("ufo_speed", &stage.ufo_speed),
+        ("drop_bomb_chance", &stage.drop_bomb_chance),
+        ("ufo_chance", &stage.ufo_chance),
+        ("ufo_turn_chance", &stage.ufo_turn_chance),
+        ("split_chance", &stage.split_chance),
+        ("max_split", &stage.max_split),
+        ("smart_bomb_chance", &stage.smart_bomb_chance),
+        ("mirv_chance", &stage.mirv_chance),
+        ("mirv_warheads", &stage.mirv_warheads),
+        ("target_scatter", &stage.target_scatter),
+        ("bonus_multiplier", &stage.bonus_multiplier),
+    ] {
+        for problem in curve.problems() {
+            problems.push(format!("{field}: {problem}"));
+        }
+    }
+    for (field, curve) in [
+        ("spawn_interval_secs", &stage.spawn_interval_secs),
+        ("split_interval_secs", &stage.split_interval_secs),
+    ] {
+        let value = curve.at(0);
+        if value <= 0.0 {
+            problems.push(format!("{field} must be above 0, found {value}"));
+        }
+    }
+    for (field, curve) in [
+        ("missile_speed", &stage.missile_speed),
+        ("ufo_speed", &stage.ufo_speed),
+        ("target_scatter", &stage.target_scatter),
+        ("bonus_multiplier", &stage.bonus_multiplier),
+    ] {
+        let value = curve.at(0);
+        if value < 0.0 {
+            problems.push(format!("{field} can't be negative, found {value}"));
+        }
+    }
+    for (field, curve) in [
+        ("drop_bomb_chance", &stage.drop_bomb_chance),
+        ("ufo_chance", &stage.ufo_chance),
+        ("ufo_turn_chance", &stage.ufo_turn_chance),
+        ("split_chance", &stage.split_chance),
+        ("smart_bomb_chance", &stage.smart_bomb_chance),
+        ("mirv_chance", &stage.mirv_chance),
+    ] {
+        let value = curve.at(0);
+        if !(0.0..=1.0).contains(&value) {
+            problems.push(format!("{field} must be between 0 and 1, found {value}"));
+        }
+    }
+    if stage.enemies_count(0) == 0 {
+        problems.push("enemies_count must be at least 1".to_string());
+    }
+    if stage.missile_spawn_max(0) == 0 {
+        problems.push("missile_spawn_max must be at least 1".to_string());
+    }
+    if stage.missile_spawn_min(0) > stage.missile_spawn_max(0) {
+        problems.push(format!(
+            "missile_spawn_min ({}) is greater than missile_spawn_max ({})",
+            stage.missile_spawn_min(0),
+            stage.missile_spawn_max(0)
+        ));
+    }
+    for (field, weight) in [
+        ("cities", stage.targeting.cities),
+        ("batteries", stage.targeting.batteries),
+        ("ground", stage.targeting.ground),
+    ] {
+        if weight < 0.0 {
+            problems.push(format!(
+                "targeting: {field} can't be negative, found {weight}"
+            ));
+        }
+    }
+    if stage.mirv_warheads(0) == 0 {
+        problems.push("mirv_warheads must be at least 1".to_string());
+    }
+    // below the top of the screen and above the cities
+    if !(32.0..SCREEN.y - 16.0).contains(&stage.mirv_split_altitude) {
+        problems.push(format!(
+            "mirv_split_altitude must be between 32 and {}, found {}",
+            SCREEN.y - 16.0,
+            stage.mirv_split_altitude
+        ));
+    }
+    if stage.waves == Some(0) {
+        problems.push("waves must be at least 1".to_string());
+    }
+    for (i, script) in stage.scripts.iter().enumerate() {
+        if stage.scripts[..i]
+            .iter()
+            .any(|other| other.wave == script.wave)
+        {
+            problems.push(format!("wave {} has more than one script", script.wave));
+        }
+        for event in script.events.iter() {
+            if event.at < 0.0 {
+                problems.push(format!(
+                    "script for wave {}: event at {} is before the wave starts",
+                    script.wave, event.at
+                ));
+            }
+            if let Spawn::Missile { x, .. } | Spawn::SmartBomb { x, .. } | Spawn::Mirv { x, .. } =
+                event.spawn
+            {
+                if x.abs() > SCREEN.x / 2.0 {
+                    problems.push(format!(
+                        "script for wave {}: missile x {x} is off screen",
+                        script.wave
+                    ));
+                }
+            }
+        }
+    }
+    let milestone_flyers = stage
+        .milestones
+        .iter()
+        .enumerate()
+        .flat_map(|(i, milestone)| {
+            milestone
+                .flyers
+                .iter()
+                .enumerate()
+                .map(move |(j, flyer)| (format!("milestone {}: flyer {}", i + 1, j + 1), flyer))
+        });
+    let flyers = stage
+        .flyers
+        .iter()
+        .enumerate()
+        .map(|(i, flyer)| (format!("flyer {}", i + 1), flyer))
+        .chain(milestone_flyers);
+    for (name, flyer) in flyers {
+        for (field, curve) in [
+            ("chance", &flyer.chance),
+            ("speed", &flyer.speed),
+            ("bomb_chance", &flyer.bomb_chance),
+            ("turn_chance", &flyer.turn_chance),
+        ] {
+            for problem in curve.problems() {
+                problems.push(format!("{name}: {field}: {problem}"));
+            }
+        }
+        for (field, curve) in [
+            ("chance", &flyer.chance),
+            ("bomb_chance", &flyer.bomb_chance),
+            ("turn_chance", &flyer.turn_chance),
+        ] {
+            let value = curve.at(0);
+            if !(0.0..=1.0).contains(&value) {
+                problems.push(format!(
+                    "{name}: {field} must be between 0 and 1, found {value}"
+                ));
+            }
+        }
+        if flyer.speed.at(0) <= 0.0 {
+            problems.push(format!(
+                "{name}: speed must be above 0, found {}",
+                flyer.speed.at(0)
+            ));
+        }
+        if flyer.bomb_interval_secs <= 0.0 {
+            problems.push(format!(
+                "{name}: bomb_interval_secs must be above 0, found {}",
+                flyer.bomb_interval_secs
+            ));
+        }
+        let [low, high] = flyer.altitude;
+        if low >= high || low < 32.0 || high + flyer.arc_height > SCREEN.y - 16.0 {
+            problems.push(format!(
+                "{name}: altitude [{low}, {high}] must be increasing and keep it between 32 and {} \
+                 above the ground",
+                SCREEN.y - 16.0
+            ));
+        }
+    }
+    for (i, power_up) in stage.power_ups.iter().enumerate() {
+        let name = format!("power-up {}", i + 1);
+        for problem in power_up.chance.problems() {
+            problems.push(format!("{name}: chance: {problem}"));
+        }
+        let chance = power_up.chance.at(0);
+        if !(0.0..=1.0).contains(&chance) {
+            problems.push(format!(
+                "{name}: chance must be between 0 and 1, found {chance}"
+            ));
+        }
+        let lasts = matches!(
+            power_up.kind,
+            PowerUpKind::BigBlast | PowerUpKind::FastMissiles | PowerUpKind::Shield
+        );
+        if lasts && power_up.duration_secs <= 0.0 {
+            problems.push(format!(
+                "{name}: duration_secs must be above 0, found {}",
+                power_up.duration_secs
+            ));
+        }
+    }
+    if stage.batteries.is_empty() {
+        problems.push("batteries must have at least 1 battery".to_string());
+    }
+    // a battery can be picked with the number keys
+    if stage.batteries.len() > MAX_BATTERIES {
+        problems.push(format!(
+            "batteries can have at most {MAX_BATTERIES}, found {}",
+            stage.batteries.len()
+        ));
+    }
+    for (i, battery) in stage.batteries.iter().enumerate() {
+        if battery.x.abs() > SCREEN.x / 2.0 {
+            problems.push(format!("battery {}: x {} is off screen", i + 1, battery.x));
+        }
+        if battery.ammo == 0 {
+            problems.push(format!("battery {}: ammo must be at least 1", i + 1));
+        }
+        if battery.cooldown_secs < 0.0 {
+            problems.push(format!(
+                "battery {}: cooldown_secs can't be negative, found {}",
+                i + 1,
+                battery.cooldown_secs
+            ));
+        }
+    }
+    for (field, color) in [
+        ("text_cor", &stage.text_cor),
+        ("bg_cor", &stage.bg_cor),
+        ("fg_cor", &stage.fg_cor),
+        ("trail_cor", &stage.trail_cor),
+    ] {
+        if color.len() != 3 {
+            problems.push(format!(
+                "{field} must have 3 elements [r, g, b], found {}",
+                color.len()
+            ));
+        }
+    }
+    for (i, milestone) in stage.milestones.iter().enumerate() {
+        let name = format!("milestone {}", i + 1);
+        if milestone.name.trim().is_empty() {
+            problems.push(format!("{name}: name is missing"));
+        }
+        for (field, chance) in [
+            ("smart_bomb_chance", milestone.smart_bomb_chance),
+            ("mirv_chance", milestone.mirv_chance),
+        ] {
+            if !(0.0..=1.0).contains(&chance) {
+                problems.push(format!(
+                    "{name}: {field} must be between 0 and 1, found {chance}"
+                ));
+            }
+        }
+        for (field, color) in [
+            ("bg_cor", &milestone.bg_cor),
+            ("fg_cor", &milestone.fg_cor),
+            ("trail_cor", &milestone.trail_cor),
+        ] {
+            if let Some(color) = color.as_ref().filter(|color| color.len() != 3) {
+                problems.push(format!(
+                    "{name}: {field} must have 3 elements [r, g, b], found {}",
+                    color.len()
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn problems_of(value: Value) -> Vec<String> {
+        parse_stage(value.to_string().as_bytes()).unwrap().problems
+    }
+
+    fn assert_reported(problems: &[String], expected: &[&str]) {
+        for expected in expected {
+            assert!(
+                problems.iter().any(|problem| problem.contains(expected)),
+                "'{expected}' was not reported in {problems:#?}"
+            );
+        }
+    }
+
+    #[test]
+    fn version_0_is_migrated_to_the_current_version() {
+        let value = migrate(json!({ "name": "Old", "spawn_rate": 2.2 })).unwrap();
+
+        assert_eq!(value["version"], json!(STAGE_VERSION));
+        assert_eq!(value["spawn_interval_secs"], json!(2.2));
+        assert!(value.get("spawn_rate").is_none());
+    }
+
+    #[test]
+    fn version_0_plays_with_the_baseline_values() {
+        let stage = parse_stage(br#"{ "name": "Old", "spawn_rate": 2.7 }"#).unwrap();
+
+        assert!(stage.problems.is_empty(), "{:?}", stage.problems);
+        for wave in [0, 3, 10] {
+            let w = wave as f32;
+            assert_eq!(
+                stage.spawn_interval_secs.at(wave),
+                (2.7 - 0.1 * w).max(1.75)
+            );
+            assert_eq!(stage.missile_speed.at(wave), (20.0 + 4.5 * w).min(50.0));
+            assert_eq!(stage.enemies_count(wave), 12 + 2 * wave);
+        }
+    }
+
+    #[test]
+    fn unversioned_file_keeps_its_fields() {
+        let value = migrate(json!({ "spawn_interval_secs": 2.0 })).unwrap();
+
+        assert_eq!(value["version"], json!(STAGE_VERSION));
+        assert_eq!(value["spawn_interval_secs"], json!(2.0));
+    }
+
+    #[test]
+    fn newer_or_broken_versions_are_rejected() {
+        assert!(migrate(json!({ "version": STAGE_VERSION + 1 })).is_err());
+        assert!(migrate(json!({ "version": "two" })).is_err());
+        assert!(migrate(json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn shipped_stages_have_no_problems() {
+        for bytes in [
+            &include_bytes!("../../assets/stages/1.stage.json")[..],
+            &include_bytes!("../../assets/stages/2.stage.json")[..],
+            &include_bytes!("../../assets/endless.stage.json")[..],
+        ] {
+            let stage = parse_stage(bytes).unwrap();
+            assert!(stage.problems.is_empty(), "{:?}", stage.problems);
+        }
+    }
+
+    #[test]
+    fn every_problem_of_a_stage_is_reported() {
+        let problems = problems_of(json!({
+            "version": 2,
+            "name": " ",
+            "spawn_interval_secs": { "constant": 0 },
+            "split_interval_secs": { "constant": -1 },
+            "enemies_count": { "constant": 0 },
+            "missile_spawn_min": { "constant": 3 },
+            "missile_spawn_max": { "constant": 0 },
+            "missile_speed": { "constant": -1 },
+            "ufo_speed": { "step": { "base": 1, "rate": 1, "every": 0 } },
+            "drop_bomb_chance": { "constant": 2 },
+            "ufo_chance": { "constant": 0.5, "min": 1, "max": 0 },
+            "split_chance": { "table": [] },
+            "smart_bomb_chance": { "table": [[2, 0.1], [1, 0.2]] },
+            "mirv_warheads": { "constant": 0 },
+            "targeting": { "cities": -1 },
+            "mirv_split_altitude": 10,
+            "waves": 0,
+            "scripts": [
+                { "wave": 0, "events": [{ "at": -1, "spawn": "missile", "x": 1000 }] },
+                { "wave": 0, "events": [] }
+            ],
+            "flyers": [{
+                "kind": "ufo",
+                "chance": 2,
+                "speed": 0,
+                "bomb_interval_secs": 0,
+                "altitude": [100, 50]
+            }],
+            "power_ups": [{ "kind": "shield", "chance": 2, "duration_secs": 0 }],
+            "batteries": [{ "x": 1000, "ammo": 0, "cooldown_secs": -1 }],
+            "text_cor": [1, 2],
+            "milestones": [{
+                "name": "",
+                "mirv_chance": 2,
+                "bg_cor": [1],
+                "flyers": [{ "kind": "bomber", "speed": 0 }]
+            }]
+        }));
+
+        assert_reported(
+            &problems,
+            &[
+                "name is missing",
+                "spawn_interval_secs must be above 0",
+                "split_interval_secs must be above 0",
+                "enemies_count must be at least 1",
+                "missile_spawn_max must be at least 1",
+                "missile_spawn_min (3) is greater than missile_spawn_max (0)",
+                "missile_speed can't be negative",
+                "ufo_speed: step every must be at least 1",
+                "drop_bomb_chance must be between 0 and 1",
+                "ufo_chance: min (1) is greater than max (0)",
+                "split_chance: table needs at least one",
+                "smart_bomb_chance: table waves must be in increasing order",
+                "mirv_warheads must be at least 1",
+                "targeting: cities can't be negative",
+                "mirv_split_altitude must be between 32",
+                "waves must be at least 1",
+                "wave 0 has more than one script",
+                "event at -1 is before the wave starts",
+                "missile x 1000 is off screen",
+                "flyer 1: chance must be between 0 and 1",
+                "flyer 1: speed must be above 0",
+                "flyer 1: bomb_interval_secs must be above 0",
+                "flyer 1: altitude [100, 50] must be increasing",
+                "power-up 1: chance must be between 0 and 1",
+                "power-up 1: duration_secs must be above 0",
+                "battery 1: x 1000 is off screen",
+                "battery 1: ammo must be at least 1",
+                "battery 1: cooldown_secs can't be negative",
+                "text_cor must have 3 elements",
+                "milestone 1: name is missing",
+                "milestone 1: mirv_chance must be between 0 and 1",
+                "milestone 1: bg_cor must have 3 elements",
+                "milestone 1: flyer 1: speed must be above 0",
+            ],
+        );
+    }
+
+    #[test]
+    fn battery_count_is_checked() {
+        let problems = problems_of(json!({ "name": "None", "batteries": [] }));
+        assert_reported(&problems, &["batteries must have at least 1 battery"]);
+
+        let batteries = vec![json!({}); MAX_BATTERIES + 1];
+        let problems = problems_of(json!({ "name": "Many", "batteries": batteries }));
+        assert_reported(&problems, &["batteries can have at most 9, found 10"]);
+    }
+
+    #[test]
+    fn malformed_file_is_a_stage_with_problems() {
+        let stage = read_stage(b"{ \"name\": ", "stages/broken.stage.json");
+
+        assert_eq!(stage.name, "stages/broken.stage.json");
+        assert_eq!(stage.problems.len(), 1);
+        assert!(stage.problems[0].starts_with("not a valid JSON file"));
+
+        let stage = read_stage(b"[]", "stages/list.stage.json");
+        assert_eq!(stage.problems, vec!["a stage file must be a JSON object"]);
+    }
+
+    #[test]
+    fn wrong_field_is_named() {
+        let stage = read_stage(
+            br#"{ "missile_speed": { "linaer": { "base": 20, "rate": 4.5 } } }"#,
+            "stages/typo.stage.json",
+        );
+        assert_reported(
+            &stage.problems,
+            &["missile_speed: expected a number or a curve"],
+        );
+
+        let stage = read_stage(
+            br#"{ "flyers": [{ "kind": "ufo", "speed": "fast" }] }"#,
+            "stages/typo.stage.json",
+        );
+        assert_reported(&stage.problems, &["flyers[0].speed: expected a number"]);
+    }
+}
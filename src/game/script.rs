@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::SCREEN;
+
+/**
+ * A hand authored wave, played back instead of the random spawner, e.g.
+ * `{ "wave": 0, "events": [{ "at": 1.0, "spawn": "missile", "x": -120.0 }] }`
+ */
+#[derive(Deserialize, Clone, Debug)]
+pub struct WaveScript {
+    // the wave of the stage this script replaces, counted from 0
+    pub wave: usize,
+    pub events: Vec<SpawnEvent>,
+    // seconds before the wave is over, defaults to the time of the last event
+    #[serde(default)]
+    pub duration: Option<f32>,
+}
+
+impl WaveScript {
+    pub fn duration(&self) -> f32 {
+        self.duration
+            .unwrap_or_else(|| self.events.last().map_or(0.0, |event| event.at))
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpawnEvent {
+    // seconds into the wave
+    pub at: f32,
+    #[serde(flatten)]
+    pub spawn: Spawn,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "spawn", rename_all = "snake_case")]
+pub enum Spawn {
+    // a missile falling from x at the top of the screen
+    Missile {
+        x: f32,
+        #[serde(default)]
+        dest_x: Option<f32>,
+    },
+    // a smart bomb falling from x, steering clear of explosions
+    SmartBomb {
+        x: f32,
+        #[serde(default)]
+        dest_x: Option<f32>,
+    },
+    // a mirv falling from x, splitting at the altitude of the stage
+    Mirv {
+        x: f32,
+        #[serde(default)]
+        dest_x: Option<f32>,
+    },
+    // a ufo flying in from one side at height y
+    Ufo {
+        from: Side,
+        y: f32,
+    },
+    // the oldest missile on screen splits
+    Split {
+        #[serde(default)]
+        count: Option<u8>,
+    },
+    // a bomber or satellite of the stage flying in from one side at height y
+    Bomber {
+        from: Side,
+        y: f32,
+    },
+    Satellite {
+        from: Side,
+        y: f32,
+    },
+    // a flyer on screen drops a bomb
+    Bomb,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    // the edge of the screen on this side
+    pub fn x(self) -> f32 {
+        match self {
+            Side::Left => -SCREEN.x / 2.0,
+            Side::Right => SCREEN.x / 2.0,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ScriptPlayback {
+    pub elapsed: f32,
+    pub next: usize,
+}
+
+impl ScriptPlayback {
+    pub fn is_finished(&self, script: &WaveScript) -> bool {
+        self.next >= script.events.len() && self.elapsed >= script.duration()
+    }
+}
@@ -1,8 +1,8 @@
-use std::ops::Add;
+use std::{f32::consts::PI, ops::Add};
 
 use bevy::{
     core_pipeline::clear_color::ClearColorConfig, ecs::query::Has, math::vec2, prelude::*,
-    window::WindowResized,
+    utils::HashMap, window::WindowResized,
 };
 use bevy_egui::{
     egui::{self, Align2, Color32, FontData, FontDefinitions, FontFamily, FontId, RichText},
@@ -10,90 +10,153 @@ use bevy_egui::{
 };
 use bevy_turborand::{DelegatedRng, GlobalRng, RngComponent};
 
-use crate::{GameState, ImageAssets, MainCamera, SCREEN};
+use crate::{GameState, ImageAssets, SCREEN};
 
 use super::{
+    bonus::BonusTally,
+    collision::swept_circles_touching,
     components::{
-        AnimationIndices, AnimationStep, AnimeRemoveOnFinish, Cannon, ChainedMeta, City, Cursor,
-        Destroyed, DropBombTimer, Enemy, Engulfable, Explodable, Explosion, ExplosionEvent,
-        ExplosionMode, FlameRadius, Foreground, Health, IdCounter, Missile, MissileArrivalEvent,
-        MissileReserve, Player, Score, ScoreGainedEvent, Scoring, SpawnPoint, Stepper, TankBody,
-        TankDestroyedEvent, TargetLock, Ufo, CITY_RESTORATION_POINTS, MAX_AMMO,
-        PLAYER_MISSILE_SPEED,
+        ActiveBattery, AnimationIndices, AnimationStep, AnimeRemoveOnFinish, Battery, Bounding,
+        Cannon, ChainedMeta, City, Cursor, Destroyed, DropBombTimer, Enemy, Engulfable, Explodable,
+        Explosion, ExplosionEvent, ExplosionMode, FlameRadius, FlightPath, Flyer,
+        FlyerDestroyedEvent, Foreground, Ground, Health, IdCounter, Mirv, Missile,
+        MissileArrivalEvent, MissileReserve, Player, Score, ScoreGainedEvent, Scoring, SmartBomb,
+        SpawnPoint, Stepper, TankBody, TankDestroyedEvent, TargetLock, CITY_HEALTH,
+        CITY_RESTORATION_POINTS, FLAME_STEP_SECS, IMPACT_RADIUS, MIRV_TELEGRAPH_SECS,
+        SMART_BOMB_EVADE_SPEED, SMART_BOMB_SIGHT, TANK_HEALTH,
     },
     effects::{Flick, TimedRemoval},
-    prelude::{color_from_vec, EnemySpawn, SplitTimer, Stage, StageHandle, Wave, WaveSpawnCount},
+    input::PlayerInput,
+    modifiers::{Modifier, RunModifiers},
+    power_ups::{ActivePowerUps, Shield},
+    prelude::{
+        color_from_vec, reset_run, EnemySpawn, FlyerKind, FlyerSpec, MilestoneEvent, RunSeed,
+        SplitTimer, Stage, StageHandle, Wave, WaveCompletedEvent, WaveSpawnCount, WaveStats,
+        MILESTONE_WAVES,
+    },
+    script::{ScriptPlayback, Spawn},
+    upgrades::{flame_steps, shot_flame_scale, RunUpgrades},
 };
 
 pub fn game_keys(
-    buttons: Res<Input<MouseButton>>,
-    keyboard: Res<Input<KeyCode>>,
-    cursor_pos: Query<&Transform, (With<Cursor>, Without<TankBody>)>,
+    input: Res<PlayerInput>,
     mut id_counter: ResMut<IdCounter>,
     mut commands: Commands,
     images: Res<ImageAssets>,
-    mut player: Query<((Entity, &mut MissileReserve), With<Player>)>,
-    mut cannon_base: Query<
-        (Entity, &mut Transform, Has<AnimationIndices>),
-        (With<TankBody>, Without<Cursor>),
-    >,
+    cannons: Query<(Entity, &Parent), With<Cannon>>,
+    mut batteries: Query<(
+        Entity,
+        &mut Transform,
+        &TankBody,
+        &mut Battery,
+        &mut MissileReserve,
+        Has<AnimationIndices>,
+    )>,
+    mut active: ResMut<ActiveBattery>,
     time: Res<Time>,
     wave: Res<Wave>,
+    mut stats: ResMut<WaveStats>,
+    power_ups: Res<ActivePowerUps>,
+    upgrades: Res<RunUpgrades>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) && wave.completion_timeout.paused() {
-        let (player_entity, mut missile_reserve) = player.single_mut().0;
-        commands.entity(player_entity).insert((
-            AnimationIndices {
-                first: 0,
-                last: 3,
-                timer: Timer::from_seconds(0.1, TimerMode::Repeating),
-            },
-            AnimeRemoveOnFinish,
-        ));
-
-        if missile_reserve.0 == 0 {
-            return;
-        } else {
-            missile_reserve.0 -= 1;
+    for (_, _, _, mut battery, _, _) in batteries.iter_mut() {
+        battery.cooldown.tick(time.delta());
+    }
+    if let Some(index) = input.battery {
+        if batteries
+            .iter()
+            .any(|(_, _, _, battery, _, _)| battery.index == index as usize)
+        {
+            active.0 = index as usize;
         }
+    }
 
-        let target_transform = cursor_pos.single();
-        let id = id_counter.next();
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: images.cursor.clone(),
-                sprite: TextureAtlasSprite::new(1),
-                transform: *target_transform,
-                ..default()
-            },
-            TargetLock(id),
-            Flick {
-                duration: Timer::from_seconds(3.0, TimerMode::Repeating),
-                switch_timer: Timer::from_seconds(0.2, TimerMode::Repeating),
-            },
-            TimedRemoval(Timer::from_seconds(3.0, TimerMode::Once)),
-        ));
+    if input.fire && wave.completion_timeout.paused() {
+        // a chosen battery fires if it can, otherwise the closest one that is ready
+        let ready: Vec<_> = batteries
+            .iter()
+            .filter(|(_, _, tank, battery, ammo, _)| {
+                matches!(tank, TankBody::Intact) && battery.cooldown.finished() && ammo.0 > 0
+            })
+            .map(|(entity, transform, _, battery, _, _)| {
+                (
+                    entity,
+                    battery.index,
+                    transform.translation.truncate().distance(input.cursor),
+                )
+            })
+            .collect();
+        let chosen = input.battery.and_then(|index| {
+            ready
+                .iter()
+                .find(|(_, battery, _)| *battery == index as usize)
+        });
+        let firing = chosen
+            .or_else(|| ready.iter().min_by(|(_, _, a), (_, _, b)| a.total_cmp(b)))
+            .and_then(|(entity, _, _)| batteries.get_mut(*entity).ok());
+
+        if let Some((battery_entity, base_transform, _, mut battery, mut missile_reserve, _)) =
+            firing
+        {
+            missile_reserve.0 -= 1;
+            battery.cooldown.reset();
+            stats.shots_fired += 1;
+
+            for (cannon, _) in cannons
+                .iter()
+                .filter(|(_, parent)| parent.get() == battery_entity)
+            {
+                commands.entity(cannon).insert((
+                    AnimationIndices {
+                        first: 0,
+                        last: 3,
+                        timer: Timer::from_seconds(0.1, TimerMode::Repeating),
+                    },
+                    AnimeRemoveOnFinish,
+                ));
+            }
 
-        let (_, base_transform, _) = cannon_base.single();
+            let id = id_counter.next();
+            commands.spawn((
+                SpriteSheetBundle {
+                    texture_atlas: images.cursor.clone(),
+                    sprite: TextureAtlasSprite::new(1),
+                    transform: Transform::from_translation(input.cursor.extend(1.0)),
+                    ..default()
+                },
+                TargetLock(id),
+                Flick {
+                    duration: Timer::from_seconds(3.0, TimerMode::Repeating),
+                    switch_timer: Timer::from_seconds(0.2, TimerMode::Repeating),
+                },
+                TimedRemoval(Timer::from_seconds(3.0, TimerMode::Once)),
+            ));
 
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: images.cursor.clone(),
-                sprite: TextureAtlasSprite::new(3),
-                transform: *base_transform,
-                ..default()
-            },
-            Missile {
-                dest: target_transform.translation.truncate(),
-                lock_id: id,
-                vel: PLAYER_MISSILE_SPEED,
-            },
-            Explodable,
-        ));
+            commands.spawn((
+                SpriteSheetBundle {
+                    texture_atlas: images.cursor.clone(),
+                    sprite: TextureAtlasSprite::new(3),
+                    transform: *base_transform,
+                    ..default()
+                },
+                Missile {
+                    dest: input.cursor,
+                    lock_id: id,
+                    vel: upgrades.missile_speed() * power_ups.missile_scale(),
+                },
+                Explodable,
+            ));
+        }
     }
 
-    let (entity, mut transform, has_anim) = cannon_base.single_mut();
-    if keyboard.any_pressed([KeyCode::A, KeyCode::Left, KeyCode::Right, KeyCode::D]) {
+    let Some((entity, mut transform, _, _, _, has_anim)) =
+        batteries.iter_mut().find(|(_, _, tank, battery, _, _)| {
+            battery.index == active.0 && matches!(tank, TankBody::Intact)
+        })
+    else {
+        return;
+    };
+    if input.left || input.right {
         if !has_anim {
             commands.entity(entity).insert((AnimationIndices {
                 first: 1,
@@ -101,11 +164,11 @@ pub fn game_keys(
                 timer: Timer::from_seconds(0.3, TimerMode::Repeating),
             },));
         }
-        if keyboard.any_pressed([KeyCode::A, KeyCode::Left]) {
-            transform.translation.x -= 50.0 * time.delta_seconds();
+        if input.left {
+            transform.translation.x -= upgrades.tank_speed() * time.delta_seconds();
         }
-        if keyboard.any_pressed([KeyCode::D, KeyCode::Right]) {
-            transform.translation.x += 50.0 * time.delta_seconds();
+        if input.right {
+            transform.translation.x += upgrades.tank_speed() * time.delta_seconds();
         }
     } else if has_anim {
         commands.entity(entity).remove::<AnimationIndices>();
@@ -118,21 +181,24 @@ pub fn gizmo_missile_trails(
     missiles: Query<(&Transform, &SpawnPoint), (With<Missile>, With<Enemy>)>,
     stage: Res<StageHandle>,
     stages: Res<Assets<Stage>>,
+    wave: Res<Wave>,
+    modifiers: Res<RunModifiers>,
 ) {
+    // the missiles come out of the fog without warning
+    if modifiers.is_active(Modifier::Fog) {
+        return;
+    }
     let stage = stages.get(&stage.0).unwrap();
+    let trail = color_from_vec(stage.palette(wave.n).trail);
 
     for (transform, spawn_point) in missiles.iter() {
-        gizmos.line_2d(
-            spawn_point.0,
-            transform.translation.truncate(),
-            color_from_vec(&stage.trail_cor),
-        );
+        gizmos.line_2d(spawn_point.0, transform.translation.truncate(), trail);
     }
 }
 
 pub fn drop_bombs(
     mut commands: Commands,
-    mut ufos: Query<(Entity, &Transform, &mut DropBombTimer), (With<Ufo>, With<Enemy>)>,
+    mut flyers: Query<(Entity, &Transform, &mut DropBombTimer), (With<Flyer>, With<Enemy>)>,
     mut id_counter: ResMut<IdCounter>,
     images: Res<ImageAssets>,
     mut global_rng: ResMut<GlobalRng>,
@@ -143,14 +209,17 @@ pub fn drop_bombs(
 ) {
     let mut rng = RngComponent::from(&mut global_rng);
     let stage = stages.get(&stage.0).unwrap();
-    for (entity, transform, mut timer) in ufos.iter_mut() {
-        timer.0.tick(time.delta());
+    for (entity, transform, mut bombs) in flyers.iter_mut() {
+        bombs.timer.tick(time.delta());
 
-        if !timer.0.just_finished() || !rng.chance(stage.drop_bomb_chance(wave.n)) {
+        if !bombs.timer.just_finished() || !rng.chance(bombs.chance) {
             continue;
         }
 
-        commands.entity(entity).remove::<DropBombTimer>();
+        bombs.bombs -= 1;
+        if bombs.bombs == 0 {
+            commands.entity(entity).remove::<DropBombTimer>();
+        }
         spawner::missile(
             &mut commands,
             &mut rng,
@@ -159,13 +228,22 @@ pub fn drop_bombs(
             &stage,
             wave.n,
             Some(transform.translation.truncate()),
+            None,
         );
     }
 }
 
 pub fn split_missiles(
     mut commands: Commands,
-    mut query: Query<(Entity, &Transform), (With<Missile>, With<Enemy>)>,
+    mut query: Query<
+        (Entity, &Transform),
+        (
+            With<Missile>,
+            With<Enemy>,
+            Without<SmartBomb>,
+            Without<Mirv>,
+        ),
+    >,
     mut id_counter: ResMut<IdCounter>,
     images: Res<ImageAssets>,
     mut global_rng: ResMut<GlobalRng>,
@@ -200,6 +278,60 @@ pub fn split_missiles(
                 &stage,
                 wave.n,
                 Some(transform.translation.truncate()),
+                None,
+            );
+        }
+    }
+}
+
+/**
+ * Flashes every mirv shortly before it reaches its split altitude, then
+ * splits it into warheads for as many different cities as there are left
+ */
+pub fn split_mirvs(
+    mut commands: Commands,
+    mut mirvs: Query<(Entity, &Transform, &Missile, &mut Mirv)>,
+    cities: Query<&Transform, (With<City>, Without<Destroyed>)>,
+    mut id_counter: ResMut<IdCounter>,
+    images: Res<ImageAssets>,
+    mut global_rng: ResMut<GlobalRng>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    wave: Res<Wave>,
+) {
+    let stage = stages.get(&stage.0).unwrap();
+    for (entity, transform, missile, mut mirv) in mirvs.iter_mut() {
+        let above = transform.translation.y - mirv.split_y;
+        if !mirv.telegraphed && above <= missile.vel * MIRV_TELEGRAPH_SECS {
+            mirv.telegraphed = true;
+            commands.entity(entity).insert(Flick {
+                duration: Timer::from_seconds(MIRV_TELEGRAPH_SECS, TimerMode::Once),
+                switch_timer: Timer::from_seconds(0.1, TimerMode::Repeating),
+            });
+        }
+        if above > 0.0 {
+            continue;
+        }
+
+        // only draws from the run when a mirv actually splits
+        let mut rng = RngComponent::from(&mut global_rng);
+        let mut targets: Vec<f32> = cities.iter().map(|city| city.translation.x).collect();
+        commands.entity(entity).despawn();
+        for _ in 0..mirv.warheads {
+            // once every city is targeted the rest land wherever
+            let dest_x = (!targets.is_empty()).then(|| {
+                let index = rng.usize(0..targets.len());
+                targets.swap_remove(index)
+            });
+            spawner::missile(
+                &mut commands,
+                &mut rng,
+                &mut id_counter,
+                images.cursor.clone(),
+                &stage,
+                wave.n,
+                Some(transform.translation.truncate()),
+                dest_x,
             );
         }
     }
@@ -210,29 +342,142 @@ pub fn is_wave_finished(
     stages: Res<Assets<Stage>>,
     wave: Res<Wave>,
     spawn_count: Res<WaveSpawnCount>,
+    playback: Res<ScriptPlayback>,
+) -> bool {
+    let stage = stages.get(&stage.0).unwrap();
+    match stage.script(wave.stage_wave()) {
+        Some(script) => playback.is_finished(script),
+        None => stage.enemies_count(wave.n) <= spawn_count.0,
+    }
+}
+
+pub fn is_wave_scripted(
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    wave: Res<Wave>,
 ) -> bool {
     let stage = stages.get(&stage.0).unwrap();
-    stage.enemies_count(wave.n) <= spawn_count.0
+    stage.script(wave.stage_wave()).is_some()
 }
 
 pub fn wave_complete(
     mut commands: Commands,
     mut wave: ResMut<Wave>,
     mut spawn_count: ResMut<WaveSpawnCount>,
-    mut missile_ammo: Query<&mut MissileReserve, With<Player>>,
+    mut playback: ResMut<ScriptPlayback>,
+    mut missile_ammo: Query<(&mut MissileReserve, &Battery, &TankBody)>,
     missiles: Query<(Entity), With<Missile>>,
+    cities: Query<(), (With<City>, Without<Destroyed>)>,
+    mut destroyed_cities: Query<
+        (Entity, &mut TextureAtlasSprite, &mut Health),
+        (With<City>, With<Destroyed>),
+    >,
+    mut stats: ResMut<WaveStats>,
+    mut wave_completed: EventWriter<WaveCompletedEvent>,
+    mut milestones: EventWriter<MilestoneEvent>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    mut upgrades: ResMut<RunUpgrades>,
+    modifiers: Res<RunModifiers>,
 ) {
+    let stage = stages.get(&stage.0).unwrap();
+    // what is left of the wave is counted before the batteries are refilled
+    let unused_ammo = missile_ammo
+        .iter()
+        .filter(|(_, _, tank)| matches!(tank, TankBody::Intact))
+        .map(|(ammo, _, _)| ammo.0 as usize)
+        .sum();
+    commands.insert_resource(BonusTally::new(
+        cities.iter().count(),
+        unused_ammo,
+        stage.city_bonus,
+        stage.ammo_bonus,
+        stage.bonus_multiplier(wave.n),
+        &stats,
+    ));
+
+    wave_completed.send(WaveCompletedEvent {
+        wave: wave.n,
+        stats: std::mem::take(&mut *stats),
+    });
     wave.n += 1;
+    if wave.n % MILESTONE_WAVES == 0 {
+        if let Some(milestone) = stage.milestone(wave.n) {
+            milestones.send(MilestoneEvent {
+                wave: wave.n,
+                name: milestone.name.clone(),
+            });
+        }
+    }
     wave.completion_timeout.unpause();
     spawn_count.0 = 0;
-    for mut ammo in missile_ammo.iter_mut() {
-        ammo.0 = MAX_AMMO;
+    *playback = ScriptPlayback::default();
+    // what is fired is gone for the run with limited ammo
+    if !modifiers.is_active(Modifier::LimitedAmmo) {
+        for (mut ammo, battery, _) in missile_ammo.iter_mut() {
+            ammo.0 = battery.ammo;
+        }
+    }
+    // the spare cities bought in the shop are rebuilt once the wave is over
+    let spares = upgrades.spare_cities;
+    for (entity, mut sprite, mut health) in destroyed_cities.iter_mut().take(spares) {
+        sprite.index = 0;
+        health.current = health.max;
+        commands.entity(entity).remove::<Destroyed>();
+        upgrades.spare_cities -= 1;
     }
     for missile in missiles.iter() {
         commands.entity(missile).despawn();
     }
 }
 
+/**
+ * Counts the bonus into the score while the next wave is on its way, all of
+ * what is left at once if the wave starts before the count is done
+ */
+pub fn tally_bonus(
+    mut tally: ResMut<BonusTally>,
+    mut score: ResMut<Score>,
+    mut score_gained_event: EventWriter<ScoreGainedEvent>,
+    wave: Res<Wave>,
+    time: Res<Time>,
+) {
+    if tally.is_finished() {
+        return;
+    }
+
+    let steps = if wave.completion_timeout.paused() {
+        usize::MAX
+    } else {
+        tally.step.tick(time.delta());
+        tally.step.times_finished_this_tick() as usize
+    };
+    for _ in 0..steps {
+        let Some(points) = tally.next() else {
+            break;
+        };
+        score.0 += points;
+        // the bonus can restore cities like any other points
+        score_gained_event.send(ScoreGainedEvent {
+            previous_score: score.0 - points,
+            current_score: score.0,
+        });
+    }
+}
+
+pub fn count_spawns(
+    mut stats: ResMut<WaveStats>,
+    spawned: Query<Has<Flyer>, (Added<Enemy>, Or<(With<Missile>, With<Flyer>)>)>,
+) {
+    for is_ufo in spawned.iter() {
+        if is_ufo {
+            stats.ufos_spawned += 1;
+        } else {
+            stats.missiles_spawned += 1;
+        }
+    }
+}
+
 pub fn tick_wave_completion(time: Res<Time>, mut wave: ResMut<Wave>) {
     wave.completion_timeout.tick(time.delta());
     if wave.completion_timeout.just_finished() {
@@ -252,6 +497,8 @@ pub fn spawn_enemies(
     stages: Res<Assets<Stage>>,
     wave: Res<Wave>,
     mut spawn_count: ResMut<WaveSpawnCount>,
+    cities: Query<&Transform, (With<City>, Without<Destroyed>)>,
+    tanks: Query<(&Transform, &TankBody)>,
 ) {
     if !wave.completion_timeout.paused() && !wave.completion_timeout.finished() {
         return;
@@ -268,56 +515,347 @@ pub fn spawn_enemies(
     let stage = stages.get(&stage.0).unwrap();
     let mut rng = RngComponent::from(&mut global_rng);
 
-    // spawn ufo
-    if rng.chance(stage.ufo_chance(wave.n)) {
-        spawner::ufo(&mut commands, &mut rng, images.cursor.clone());
-        spawn_count.0 += 1;
+    // spawn flyers
+    for flyer in stage.flyers(wave.n) {
+        if rng.chance(flyer.chance) {
+            spawner::flyer(&mut commands, &mut rng, images.cursor.clone(), &flyer);
+            spawn_count.0 += 1;
+        }
     }
 
-    for _ in 0..=rng.usize(stage.missile_spawn_min(wave.n)..stage.missile_spawn_max(wave.n)) {
-        spawner::missile(
-            &mut commands,
-            &mut rng,
-            &mut id_counter,
-            images.cursor.clone(),
-            &stage,
-            wave.n,
-            None,
-        );
+    // curves can cross at high waves, the range must never be empty
+    let max = stage.missile_spawn_max(wave.n).max(1);
+    let min = stage.missile_spawn_min(wave.n).min(max - 1);
+    let cities: Vec<f32> = cities.iter().map(|city| city.translation.x).collect();
+    let batteries: Vec<f32> = tanks
+        .iter()
+        .filter(|(_, tank)| matches!(tank, TankBody::Intact))
+        .map(|(transform, _)| transform.translation.x)
+        .collect();
+    for _ in 0..=rng.usize(min..max) {
+        // no draw for stages without smart bombs or mirvs, so their runs play out as before
+        let smart_bomb_chance = stage.smart_bomb_chance(wave.n);
+        let mirv_chance = stage.mirv_chance(wave.n);
+        let dest_x = aim_missile(&mut rng, stage, wave.n, &cities, &batteries);
+        if mirv_chance > 0.0 && rng.chance(mirv_chance) {
+            spawner::mirv(
+                &mut commands,
+                &mut rng,
+                &mut id_counter,
+                images.cursor.clone(),
+                &stage,
+                wave.n,
+                None,
+                dest_x,
+            );
+        } else if smart_bomb_chance > 0.0 && rng.chance(smart_bomb_chance) {
+            spawner::smart_bomb(
+                &mut commands,
+                &mut rng,
+                &mut id_counter,
+                images.cursor.clone(),
+                &stage,
+                wave.n,
+                None,
+                dest_x,
+            );
+        } else {
+            spawner::missile(
+                &mut commands,
+                &mut rng,
+                &mut id_counter,
+                images.cursor.clone(),
+                &stage,
+                wave.n,
+                None,
+                dest_x,
+            );
+        }
         spawn_count.0 += 1;
     }
 }
 
-pub fn change_colors(mut query: Query<&mut Sprite, With<TargetLock>>) {
-    for mut sprite in query.iter_mut() {
-        sprite.color = Color::rgb(1.0, 0.0, 0.0);
+/**
+ * Where a randomly spawned missile is headed: an intact city or battery give
+ * or take the scatter of the wave, or `None` for anywhere on the ground
+ */
+fn aim_missile(
+    rng: &mut RngComponent,
+    stage: &Stage,
+    wave: usize,
+    cities: &[f32],
+    batteries: &[f32],
+) -> Option<f32> {
+    let weights = &stage.targeting;
+    let city = if cities.is_empty() {
+        0.0
+    } else {
+        weights.cities
+    };
+    let battery = if batteries.is_empty() {
+        0.0
+    } else {
+        weights.batteries
+    };
+    // stages that only aim at the ground draw nothing, so their runs play out as before
+    if city + battery <= 0.0 {
+        return None;
     }
+
+    let roll = rng.f32() * (city + battery + weights.ground);
+    let targets = if roll < city {
+        cities
+    } else if roll < city + battery {
+        batteries
+    } else {
+        return None;
+    };
+    let x = targets[rng.usize(0..targets.len())];
+    let scatter = stage.target_scatter(wave) * (rng.f32() * 2.0 - 1.0);
+
+    Some((x + scatter).clamp(-SCREEN.x / 2.0, SCREEN.x / 2.0))
 }
 
-pub fn move_ufo(
+/**
+ * Plays back the script of the current wave. Replaces `spawn_enemies`,
+ * `split_missiles` and `drop_bombs` for scripted waves.
+ */
+pub fn play_wave_script(
+    mut id_counter: ResMut<IdCounter>,
     mut commands: Commands,
-    mut ufos: Query<(Entity, &Ufo, &mut Transform)>,
+    images: Res<ImageAssets>,
+    mut global_rng: ResMut<GlobalRng>,
     time: Res<Time>,
     stage: Res<StageHandle>,
     stages: Res<Assets<Stage>>,
     wave: Res<Wave>,
+    mut playback: ResMut<ScriptPlayback>,
+    mut spawn_count: ResMut<WaveSpawnCount>,
+    missiles: Query<
+        (Entity, &Transform, &Missile),
+        (With<Enemy>, Without<SmartBomb>, Without<Mirv>),
+    >,
+    flyers: Query<&Transform, (With<Flyer>, With<Enemy>)>,
 ) {
+    if !wave.completion_timeout.paused() && !wave.completion_timeout.finished() {
+        return;
+    }
+
     let stage = stages.get(&stage.0).unwrap();
-    for (entity, ufo, mut transform) in ufos.iter_mut() {
-        let dir = ufo.0 - transform.translation.truncate();
-        let dist = dir.length();
-        let translation = dir.normalize() * stage.ufo_speed(wave.n) * time.delta_seconds();
-        if dist > translation.length() {
-            // move the ufo
-            transform.translation += translation.extend(0.0);
-        } else {
-            // ufo has arrived at target
-            // despawn ufo
-            commands.entity(entity).despawn();
+    let Some(script) = stage.script(wave.stage_wave()) else {
+        return;
+    };
+    let mut rng = RngComponent::from(&mut global_rng);
+    let flyers_of_stage = stage.flyers(wave.n);
+
+    playback.elapsed += time.delta_seconds();
+    while let Some(event) = script
+        .events
+        .get(playback.next)
+        .filter(|event| event.at <= playback.elapsed)
+    {
+        playback.next += 1;
+
+        match event.spawn {
+            Spawn::Missile { x, dest_x } => {
+                spawner::missile(
+                    &mut commands,
+                    &mut rng,
+                    &mut id_counter,
+                    images.cursor.clone(),
+                    &stage,
+                    wave.n,
+                    Some(vec2(x, SCREEN.y / 2.0)),
+                    dest_x,
+                );
+                spawn_count.0 += 1;
+            }
+            Spawn::SmartBomb { x, dest_x } => {
+                spawner::smart_bomb(
+                    &mut commands,
+                    &mut rng,
+                    &mut id_counter,
+                    images.cursor.clone(),
+                    &stage,
+                    wave.n,
+                    Some(vec2(x, SCREEN.y / 2.0)),
+                    dest_x,
+                );
+                spawn_count.0 += 1;
+            }
+            Spawn::Mirv { x, dest_x } => {
+                spawner::mirv(
+                    &mut commands,
+                    &mut rng,
+                    &mut id_counter,
+                    images.cursor.clone(),
+                    &stage,
+                    wave.n,
+                    Some(vec2(x, SCREEN.y / 2.0)),
+                    dest_x,
+                );
+                spawn_count.0 += 1;
+            }
+            Spawn::Ufo { from, y } => {
+                spawner::flyer_at(
+                    &mut commands,
+                    images.cursor.clone(),
+                    &flyers_of_stage[0],
+                    vec2(from.x(), y),
+                );
+                spawn_count.0 += 1;
+            }
+            Spawn::Bomber { from, y } | Spawn::Satellite { from, y } => {
+                let kind = match event.spawn {
+                    Spawn::Bomber { .. } => FlyerKind::Bomber,
+                    _ => FlyerKind::Satellite,
+                };
+                // a kind the stage doesn't list flies with the defaults of the kind
+                let flyer = flyers_of_stage
+                    .iter()
+                    .find(|flyer| flyer.kind == kind)
+                    .copied()
+                    .unwrap_or_else(|| FlyerSpec::default_for(kind).at(wave.n));
+                spawner::flyer_at(
+                    &mut commands,
+                    images.cursor.clone(),
+                    &flyer,
+                    vec2(from.x(), y),
+                );
+                spawn_count.0 += 1;
+            }
+            Spawn::Split { count } => {
+                let oldest = missiles
+                    .iter()
+                    .min_by_key(|(_, _, missile)| missile.lock_id);
+                if let Some((entity, transform, _)) = oldest {
+                    commands.entity(entity).despawn();
+                    for _ in 0..count.unwrap_or(stage.max_split(wave.n)) {
+                        spawner::missile(
+                            &mut commands,
+                            &mut rng,
+                            &mut id_counter,
+                            images.cursor.clone(),
+                            &stage,
+                            wave.n,
+                            Some(transform.translation.truncate()),
+                            None,
+                        );
+                    }
+                }
+            }
+            Spawn::Bomb => {
+                if let Some(transform) = flyers.iter().next() {
+                    spawner::missile(
+                        &mut commands,
+                        &mut rng,
+                        &mut id_counter,
+                        images.cursor.clone(),
+                        &stage,
+                        wave.n,
+                        Some(transform.translation.truncate()),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn change_colors(mut query: Query<&mut Sprite, With<TargetLock>>) {
+    for mut sprite in query.iter_mut() {
+        sprite.color = Color::rgb(1.0, 0.0, 0.0);
+    }
+}
+
+pub fn move_flyers(
+    mut commands: Commands,
+    mut flyers: Query<(Entity, &mut Flyer, &mut Transform)>,
+    mut global_rng: ResMut<GlobalRng>,
+    time: Res<Time>,
+) {
+    for (entity, mut flyer, mut transform) in flyers.iter_mut() {
+        flyer.turn_timer.tick(time.delta());
+        // only draws from the run for flyers that can turn
+        if flyer.turn_timer.just_finished()
+            && flyer.turn_chance > 0.0
+            && RngComponent::from(&mut global_rng).chance(flyer.turn_chance)
+        {
+            if let FlightPath::Straight(ref mut to) = flyer.path {
+                to.x = -to.x;
+            }
+        }
+
+        let speed = flyer.speed;
+        match flyer.path {
+            FlightPath::Straight(to) => {
+                let dir = to - transform.translation.truncate();
+                let dist = dir.length();
+                let translation = dir.normalize() * speed * time.delta_seconds();
+                if dist > translation.length() {
+                    // move the flyer
+                    transform.translation += translation.extend(0.0);
+                } else {
+                    // flyer has arrived at target
+                    // despawn flyer
+                    commands.entity(entity).despawn();
+                }
+            }
+            FlightPath::Arc {
+                from,
+                to,
+                height,
+                ref mut progress,
+            } => {
+                *progress += speed * time.delta_seconds() / from.distance(to).max(f32::EPSILON);
+                if *progress < 1.0 {
+                    let pos = from.lerp(to, *progress) + Vec2::Y * height * (PI * *progress).sin();
+                    transform.translation = pos.extend(transform.translation.z);
+                } else {
+                    commands.entity(entity).despawn();
+                }
+            }
         }
     }
 }
 
+/**
+ * Smart bombs sidestep the flames they see coming, away from the middle of
+ * each explosion. Explosions on both sides cancel out, so a smart bomb has to
+ * be bracketed.
+ */
+pub fn evade_explosions(
+    mut smart_bombs: Query<&mut Transform, With<SmartBomb>>,
+    flames: Query<(&Transform, &Stepper<FlameRadius, i32>), Without<SmartBomb>>,
+    time: Res<Time>,
+) {
+    for mut transform in smart_bombs.iter_mut() {
+        let pos = transform.translation.truncate();
+        let push: f32 = flames
+            .iter()
+            .filter_map(|(flame_transform, stepper)| {
+                let reached = stepper
+                    .current
+                    .checked_sub(1)
+                    .and_then(|step| stepper.steps.get(step))
+                    .copied()
+                    .unwrap_or(0);
+                // dying flames are no threat
+                let upcoming = *stepper.current()?;
+                if upcoming < reached {
+                    return None;
+                }
+                let away = pos - flame_transform.translation.truncate();
+                (away.length() < upcoming as f32 + SMART_BOMB_SIGHT).then(|| away.x.signum())
+            })
+            .sum();
+
+        let x = transform.translation.x
+            + push.clamp(-1.0, 1.0) * SMART_BOMB_EVADE_SPEED * time.delta_seconds();
+        transform.translation.x = x.clamp(-SCREEN.x / 2.0, SCREEN.x / 2.0);
+    }
+}
+
 pub fn move_missile(
     mut missiles: Query<(Entity, &Missile, &mut Transform, Has<Enemy>), Without<TargetLock>>,
     mut missile_arrival_evnt: EventWriter<MissileArrivalEvent>,
@@ -342,30 +880,24 @@ pub fn move_missile(
     }
 }
 
-pub fn move_cursor(
-    mut cursor: Query<(&mut Transform, &Cursor)>,
-    windows: Query<&Window>,
-    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
-) {
-    let window = windows.single();
-    let (camera, camera_transform) = camera_q.single();
-
-    if let Some(world_position) = window
-        .cursor_position()
-        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
-    {
-        for (mut transform, _) in cursor.iter_mut() {
-            transform.translation = world_position.extend(1.0);
-        }
+pub fn move_cursor(mut cursor: Query<&mut Transform, With<Cursor>>, input: Res<PlayerInput>) {
+    for mut transform in cursor.iter_mut() {
+        transform.translation = input.cursor.extend(1.0);
     }
 }
 
 pub fn rotate_player(
     cannon_base: Query<(&Transform, &TankBody), (Without<Cursor>, Without<Cannon>)>,
-    mut cannon: Query<&mut Transform, (With<Cannon>, Without<Cursor>, Without<TankBody>)>,
+    mut cannons: Query<
+        (&mut Transform, &Parent),
+        (With<Cannon>, Without<Cursor>, Without<TankBody>),
+    >,
     cursor: Query<&Transform, (With<Cursor>, Without<TankBody>, Without<Cannon>)>,
 ) {
-    for (transform, tank_body) in cannon_base.iter() {
+    for (mut cannon_transform, parent) in cannons.iter_mut() {
+        let Ok((transform, tank_body)) = cannon_base.get(parent.get()) else {
+            continue;
+        };
         match tank_body {
             TankBody::Destroyed => {}
             TankBody::Intact => {
@@ -375,9 +907,7 @@ pub fn rotate_player(
                     let b = cursor.translation.truncate();
                     let direction = b - a;
                     let angle = direction.y.atan2(direction.x) - 90.0_f32.to_radians();
-                    for mut transform in cannon.iter_mut() {
-                        transform.rotation = Quat::from_rotation_z(angle);
-                    }
+                    cannon_transform.rotation = Quat::from_rotation_z(angle);
                 }
             }
         }
@@ -385,11 +915,18 @@ pub fn rotate_player(
 }
 
 pub fn reset_game_listener(
+    mut commands: Commands,
     keyboard: Res<Input<KeyCode>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
+    // a retry starts the stage over as a new run
     if keyboard.just_pressed(KeyCode::R) {
-        next_state.set(GameState::InGame);
+        reset_run(&mut commands);
+        next_state.set(GameState::EnterGame);
+    }
+    if keyboard.just_pressed(KeyCode::Escape) {
+        reset_run(&mut commands);
+        next_state.set(GameState::MainMenu);
     }
 }
 
@@ -469,6 +1006,7 @@ pub fn explosion_system(
                             remaining: meta.remaining - 1,
                             timer: meta.timer.clone(),
                         }),
+                        shot: false,
                     });
 
                     // need to prevent the same explosion to be handled multiple times
@@ -484,8 +1022,17 @@ pub fn explosion_event_listener_system(
     mut commands: Commands,
     mut explosion_event: EventReader<ExplosionEvent>,
     images: Res<ImageAssets>,
+    power_ups: Res<ActivePowerUps>,
+    upgrades: Res<RunUpgrades>,
+    modifiers: Res<RunModifiers>,
 ) {
-    for ExplosionEvent { pos, mode } in explosion_event.iter() {
+    for ExplosionEvent { pos, mode, shot } in explosion_event.iter() {
+        // only the shots of the player grow with upgrades, power-ups and modifiers
+        let scale = if *shot {
+            shot_flame_scale(&power_ups, &upgrades, &modifiers)
+        } else {
+            1.0
+        };
         let explosion_mode = match mode {
             ExplosionMode::Single => mode.clone(),
             ExplosionMode::Chained(meta) => ExplosionMode::Chained(ChainedMeta {
@@ -499,7 +1046,7 @@ pub fn explosion_event_listener_system(
                 sprite: TextureAtlasSprite::new(0),
                 transform: Transform {
                     translation: *pos,
-                    // scale: Vec3::splat(3.0),
+                    scale: Vec3::splat(scale),
                     ..default()
                 },
                 ..default()
@@ -513,42 +1060,83 @@ pub fn explosion_event_listener_system(
             Stepper {
                 marker: FlameRadius {},
                 current: 0,
-                steps: Vec::from([2, 8, 12, 16, 16, 16, 12, 12, 8, 2]),
-                timer: Timer::from_seconds(0.1, TimerMode::Repeating),
+                steps: flame_steps(scale),
+                timer: Timer::from_seconds(FLAME_STEP_SECS, TimerMode::Repeating),
             },
-            Explosion::new(explosion_mode),
+            Explosion::new(explosion_mode, *shot),
             Foreground,
         ));
     }
 }
 
-pub fn explode_city(
+/**
+ * Every enemy missile that hits the ground takes a hit off every city and
+ * battery within its blast, destroying what has nothing left
+ */
+pub fn damage_on_impact(
     mut commands: Commands,
-    mut cities: Query<(Entity, &Transform, &mut TextureAtlasSprite), With<City>>,
-    flames: Query<(&Transform, &Stepper<FlameRadius, i32>), With<Explosion>>,
+    mut arrivals: EventReader<MissileArrivalEvent>,
+    mut cities: Query<
+        (Entity, &Transform, &mut Health, &mut TextureAtlasSprite),
+        (With<City>, Without<Destroyed>, Without<Shield>),
+    >,
+    mut tanks: Query<(Entity, &Transform, &mut Health, &TankBody), Without<City>>,
+    mut explosion_event: EventWriter<ExplosionEvent>,
+    mut tank_destruction: EventWriter<TankDestroyedEvent>,
+    mut stats: ResMut<WaveStats>,
 ) {
-    for (city_entity, city_transform, mut city_sprite) in cities.iter_mut() {
-        for (flame_transform, flame_stepper) in flames.iter() {
-            let distance = city_transform
-                .translation
-                .truncate()
-                .distance(flame_transform.translation.truncate());
-            if distance < flame_stepper.current as f32 + 16.0 {
-                city_sprite.index = 1;
-                commands.entity(city_entity).insert(Destroyed);
+    for arrival in arrivals.iter().filter(|arrival| arrival.is_enemy) {
+        let impact = arrival.missile.dest;
+        for (entity, transform, mut health, mut sprite) in cities.iter_mut() {
+            // already destroyed by another hit this frame
+            if health.current == 0
+                || transform.translation.truncate().distance(impact) > IMPACT_RADIUS
+            {
+                continue;
+            }
+
+            if health.hit() {
+                sprite.index = 1;
+                commands.entity(entity).insert(Destroyed);
+                stats.cities_lost += 1;
+            } else {
+                sprite.index = 2;
+            }
+        }
+
+        for (entity, transform, mut health, tank) in tanks.iter_mut() {
+            if matches!(tank, TankBody::Destroyed)
+                || health.current == 0
+                || transform.translation.truncate().distance(impact) > IMPACT_RADIUS
+            {
+                continue;
+            }
+
+            if health.hit() {
+                explosion_event.send(ExplosionEvent {
+                    pos: transform.translation,
+                    mode: ExplosionMode::Chained(ChainedMeta {
+                        timer: Timer::from_seconds(0.2, TimerMode::Repeating),
+                        remaining: 3,
+                    }),
+                    shot: false,
+                });
+                tank_destruction.send(TankDestroyedEvent(entity));
             }
         }
     }
 }
 
 pub fn player_destruction(
+    mut commands: Commands,
     mut tank_destruction: EventReader<TankDestroyedEvent>,
     mut tank: Query<(&mut TankBody, &mut TextureAtlasSprite)>,
 ) {
-    for _ in tank_destruction.iter() {
-        for (mut tank, mut sprite) in tank.iter_mut() {
+    for TankDestroyedEvent(entity) in tank_destruction.iter() {
+        if let Ok((mut tank, mut sprite)) = tank.get_mut(*entity) {
             *tank = TankBody::Destroyed;
             sprite.index = 0;
+            commands.entity(*entity).remove::<AnimationIndices>();
         }
     }
 }
@@ -557,17 +1145,18 @@ pub fn defeat(
     mut next_state: ResMut<NextState<GameState>>,
     cities: Query<(&Transform, &mut TextureAtlasSprite), (With<City>, Without<Destroyed>)>,
     tank: Query<&TankBody>,
+    mut score: ResMut<Score>,
+    modifiers: Res<RunModifiers>,
 ) {
-    if cities.iter().count() == 0 {
+    // the player is overrun once every city or every battery is gone
+    let destroyed = |tank: &TankBody| match tank {
+        TankBody::Intact => false,
+        TankBody::Destroyed => true,
+    };
+    if cities.iter().count() == 0 || (!tank.is_empty() && tank.iter().all(destroyed)) {
         next_state.set(GameState::GameOver);
-    }
-    for tank in tank.iter() {
-        match tank {
-            TankBody::Intact => {}
-            TankBody::Destroyed => {
-                next_state.set(GameState::GameOver);
-            }
-        }
+        // the modifiers of the run weigh in on its final score
+        score.0 = modifiers.final_score(score.0);
     }
 }
 
@@ -595,30 +1184,26 @@ pub fn missile_arrival_event_listner(
         explosion_event.send(ExplosionEvent {
             pos: missile.dest.extend(1.0),
             mode: ExplosionMode::Single,
+            shot: !is_enemy,
         });
         commands.entity(*id).despawn();
-
-        // // Damage player
-        // if *is_enemy {
-        //     let mut health = player_health.single_mut();
-        //     if health.current > 0 {
-        //         health.current -= 1;
-        //     }
-
-        //     // should be moved to a separate system
-        //     if health.current == 0 {
-        //         next_state.set(GameState::GameOver);
-        //     }
-        // }
     }
 }
 
 pub fn score_gained_event_listener(
     mut commands: Commands,
     mut score_gained_evnt: EventReader<ScoreGainedEvent>,
-    mut cities: Query<(Entity, &mut TextureAtlasSprite), (With<City>, With<Destroyed>)>,
+    mut cities: Query<
+        (Entity, &mut TextureAtlasSprite, &mut Health),
+        (With<City>, With<Destroyed>),
+    >,
     mut global_rng: ResMut<GlobalRng>,
+    modifiers: Res<RunModifiers>,
 ) {
+    if modifiers.is_active(Modifier::NoCityRestoration) {
+        return;
+    }
+
     for ScoreGainedEvent {
         previous_score,
         current_score,
@@ -630,8 +1215,9 @@ pub fn score_gained_event_listener(
         {
             let mut rng = RngComponent::from(&mut global_rng);
             let index = rng.usize(0..cities.len());
-            if let Some((entity, mut sprite)) = cities.into_iter().nth(index) {
+            if let Some((entity, mut sprite, mut health)) = cities.into_iter().nth(index) {
                 sprite.index = 0;
+                health.current = health.max;
                 commands.entity(entity).remove::<Destroyed>();
             }
         }
@@ -642,7 +1228,7 @@ pub fn despawns(
     mut commands: Commands,
     wave: Res<Wave>,
     missiles: Query<(Entity, &Transform), With<Missile>>,
-    ufos: Query<(Entity, &Transform), With<Ufo>>,
+    flyers: Query<(Entity, &Transform), With<Flyer>>,
     mut explosion_event: EventWriter<ExplosionEvent>,
 ) {
     if wave.completion_timeout.paused() || wave.completion_timeout.finished() {
@@ -653,18 +1239,24 @@ pub fn despawns(
         explosion_event.send(ExplosionEvent {
             pos: mt.translation,
             mode: ExplosionMode::Single,
+            shot: false,
         });
         commands.entity(missile).despawn();
     }
-    for (ufo, ut) in ufos.iter() {
+    for (flyer, ft) in flyers.iter() {
         explosion_event.send(ExplosionEvent {
-            pos: ut.translation,
+            pos: ft.translation,
             mode: ExplosionMode::Single,
+            shot: false,
         });
-        commands.entity(ufo).despawn();
+        commands.entity(flyer).despawn();
     }
 }
 
+/**
+ * The flames of every explosion take the enemies whose path since the last
+ * tick came within their radius, however fast they flew
+ */
 pub fn flame_engulf_system(
     mut commands: Commands,
     time: Res<Time>,
@@ -675,73 +1267,119 @@ pub fn flame_engulf_system(
         &mut Explosion,
         Without<Engulfable>,
     )>,
-    mut engulfables: Query<(
+    engulfables: Query<(
         Entity,
         &Transform,
         With<Engulfable>,
         Has<Missile>,
-        Has<TankBody>,
+        Has<SmartBomb>,
+        Has<Mirv>,
+        Option<&Flyer>,
+        &Bounding,
     )>,
+    // where every enemy was at the last tick
+    mut last_positions: Local<HashMap<Entity, Vec3>>,
     mut score: ResMut<Score>,
     mut explosion_event: EventWriter<ExplosionEvent>,
-    mut player_destruction_event: EventWriter<TankDestroyedEvent>,
     mut score_gained_event: EventWriter<ScoreGainedEvent>,
+    mut flyer_destruction_event: EventWriter<FlyerDestroyedEvent>,
+    mut stats: ResMut<WaveStats>,
 ) {
+    let mut engulfed = Vec::new();
     for (flame_entity, flame_transform, mut stepper, mut expl, _) in flames.iter_mut() {
         stepper.timer.tick(time.delta());
-        if !stepper.timer.just_finished() {
-            return;
+        if stepper.timer.just_finished() {
+            if stepper.is_finished() {
+                if expl.shot && expl.combo > 0 {
+                    stats.shots_hit += 1;
+                }
+                stats.largest_combo = stats.largest_combo.max(expl.combo);
+                score.0 += expl.calculated_score();
+                score_gained_event.send(ScoreGainedEvent {
+                    previous_score: score.0 - expl.calculated_score(),
+                    current_score: score.0,
+                });
+                commands
+                    .entity(flame_entity)
+                    .remove::<Stepper<FlameRadius, i32>>();
+                continue;
+            }
+            stepper.next();
         }
 
-        if stepper.is_finished() {
-            score.0 += expl.calculated_score();
-            score_gained_event.send(ScoreGainedEvent {
-                previous_score: score.0 - expl.calculated_score(),
-                current_score: score.0,
-            });
-            commands
-                .entity(flame_entity)
-                .remove::<Stepper<FlameRadius, i32>>();
-        } else {
-            if let Some(radius) = stepper.next() {
-                for (entity, transform, _, is_missile, is_tank_body) in engulfables.iter_mut() {
-                    let distance = flame_transform.translation.distance(transform.translation);
-                    if distance > *radius as f32 {
-                        continue;
-                    }
+        // the flames burn at the radius of their last step until the next
+        let Some(radius) = stepper
+            .current
+            .checked_sub(1)
+            .and_then(|step| stepper.steps.get(step))
+        else {
+            continue;
+        };
+        let flame = Bounding(*radius as f32);
+        for (entity, transform, _, is_missile, is_smart_bomb, is_mirv, flyer, bounding) in
+            engulfables.iter()
+        {
+            let from = last_positions
+                .get(&entity)
+                .unwrap_or(&transform.translation);
+            if engulfed.contains(&entity)
+                || !swept_circles_touching(
+                    from,
+                    &transform.translation,
+                    bounding,
+                    flame_transform,
+                    &flame,
+                )
+            {
+                continue;
+            }
+            engulfed.push(entity);
 
-                    if is_missile {
-                        explosion_event.send(ExplosionEvent {
-                            pos: transform.translation,
-                            mode: ExplosionMode::Single,
-                        });
-                        commands.entity(entity).despawn();
-                        expl.add_score(Scoring::Missile);
-                    } else if is_tank_body {
-                        explosion_event.send(ExplosionEvent {
-                            pos: transform.translation,
-                            mode: ExplosionMode::Chained(ChainedMeta {
-                                timer: Timer::from_seconds(0.2, TimerMode::Repeating),
-                                remaining: 3,
-                            }),
-                        });
-                        player_destruction_event.send(TankDestroyedEvent);
-                    } else {
-                        // is ufo, more points
-                        explosion_event.send(ExplosionEvent {
-                            pos: transform.translation,
-                            mode: ExplosionMode::Chained(ChainedMeta {
-                                timer: Timer::from_seconds(0.2, TimerMode::Repeating),
-                                remaining: 5,
-                            }),
-                        });
-                        commands.entity(entity).despawn();
-                        expl.add_score(Scoring::Ufo);
-                    }
+            if is_missile {
+                explosion_event.send(ExplosionEvent {
+                    pos: transform.translation,
+                    mode: ExplosionMode::Single,
+                    shot: false,
+                });
+                commands.entity(entity).despawn();
+                expl.add_score(if is_mirv {
+                    Scoring::Mirv
+                } else if is_smart_bomb {
+                    Scoring::SmartBomb
+                } else {
+                    Scoring::Missile
+                });
+                stats.missiles_destroyed += 1;
+            } else {
+                // is a flyer, more points
+                explosion_event.send(ExplosionEvent {
+                    pos: transform.translation,
+                    mode: ExplosionMode::Chained(ChainedMeta {
+                        timer: Timer::from_seconds(0.2, TimerMode::Repeating),
+                        remaining: 5,
+                    }),
+                    shot: false,
+                });
+                commands.entity(entity).despawn();
+                expl.add_points(flyer.map_or(Scoring::Ufo as usize, |flyer| flyer.score));
+                stats.ufos_destroyed += 1;
+                if let Some(flyer) = flyer {
+                    flyer_destruction_event.send(FlyerDestroyedEvent {
+                        pos: transform.translation,
+                        kind: flyer.kind,
+                    });
                 }
             }
         }
     }
+
+    // the next tick tests the paths from here
+    last_positions.clear();
+    last_positions.extend(
+        engulfables
+            .iter()
+            .map(|(entity, transform, ..)| (entity, transform.translation)),
+    );
 }
 
 pub fn teardown_in_game(
@@ -767,6 +1405,15 @@ pub fn teardown_in_game(
     }
 }
 
+pub fn teardown_scenery(
+    mut commands: Commands,
+    scenery: Query<Entity, Or<(With<City>, With<Ground>, With<TankBody>)>>,
+) {
+    for entity in scenery.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 pub fn teardown_game_over(mut commands: Commands, tank_body: Query<Entity, With<TankBody>>) {
     for entity in tank_body.iter() {
         commands.entity(entity).despawn_recursive();
@@ -777,7 +1424,14 @@ pub fn teardown_game_over(mut commands: Commands, tank_body: Query<Entity, With<
  * Systems that are called once at the start of the game
  */
 
-pub fn setup_player(mut commands: Commands, images: Res<ImageAssets>) {
+pub fn setup_player(
+    mut commands: Commands,
+    images: Res<ImageAssets>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    upgrades: Res<RunUpgrades>,
+    modifiers: Res<RunModifiers>,
+) {
     commands.spawn((
         SpriteSheetBundle {
             texture_atlas: images.ground.clone(),
@@ -785,6 +1439,7 @@ pub fn setup_player(mut commands: Commands, images: Res<ImageAssets>) {
             transform: Transform::from_translation(Vec3::new(0.0, -SCREEN.y / 2.0 + 16.0, 1.0)),
             ..default()
         },
+        Ground,
         Foreground,
     ));
 
@@ -799,37 +1454,54 @@ pub fn setup_player(mut commands: Commands, images: Res<ImageAssets>) {
         Foreground,
     ));
 
-    let cannon = commands
-        .spawn((
+    let stage = stages.get(&stage.0).unwrap();
+    let batteries = modifiers.batteries(upgrades.batteries(&stage.batteries));
+    for (index, layout) in batteries.iter().enumerate() {
+        let cannon = commands
+            .spawn((
+                SpriteSheetBundle {
+                    texture_atlas: images.cannon.clone(),
+                    sprite: TextureAtlasSprite::new(0),
+                    transform: Transform::from_translation(Vec3::new(0.0, -2.0, 1.0)),
+                    ..default()
+                },
+                Player,
+                Cannon,
+                Foreground,
+            ))
+            .id();
+
+        // ready to fire from the start
+        let mut cooldown = Timer::from_seconds(layout.cooldown_secs, TimerMode::Once);
+        cooldown.tick(cooldown.duration());
+        let mut tank = commands.spawn((
             SpriteSheetBundle {
-                texture_atlas: images.cannon.clone(),
-                sprite: TextureAtlasSprite::new(0),
-                transform: Transform::from_translation(Vec3::new(0.0, -2.0, 1.0)),
+                texture_atlas: images.tank.clone(),
+                sprite: TextureAtlasSprite::new(1),
+                transform: Transform::from_translation(Vec3::new(
+                    layout.x,
+                    -SCREEN.y / 2.0 + 24.0,
+                    2.0,
+                )),
                 ..default()
             },
-            Player,
-            Cannon,
-            Health { max: 3, current: 3 },
-            MissileReserve(MAX_AMMO),
+            TankBody::Intact,
+            Battery {
+                index,
+                ammo: layout.ammo,
+                cooldown,
+            },
+            MissileReserve(layout.ammo),
+            Health::full(TANK_HEALTH),
             Foreground,
-        ))
-        .id();
-
-    let mut tank = commands.spawn((
-        SpriteSheetBundle {
-            texture_atlas: images.tank.clone(),
-            sprite: TextureAtlasSprite::new(1),
-            transform: Transform::from_translation(Vec3::new(0.0, -SCREEN.y / 2.0 + 24.0, 2.0)),
-            ..default()
-        },
-        TankBody::Intact,
-        Engulfable,
-        Foreground,
-    ));
+        ));
 
-    tank.add_child(cannon);
+        tank.add_child(cannon);
+    }
+    // the middle battery is the one moved to begin with
+    commands.insert_resource(ActiveBattery(batteries.len() / 2));
 
-    for i in 0..6 {
+    for i in (0..6).filter(|i| modifiers.keeps_city(*i)) {
         commands.spawn((
             SpriteSheetBundle {
                 texture_atlas: images.city.clone(),
@@ -842,6 +1514,7 @@ pub fn setup_player(mut commands: Commands, images: Res<ImageAssets>) {
                 ..default()
             },
             City,
+            Health::full(CITY_HEALTH),
             Foreground,
         ));
     }
@@ -850,47 +1523,108 @@ pub fn setup_player(mut commands: Commands, images: Res<ImageAssets>) {
 /* UI
  * Systems that are called every frame to update the egui UI
  */
-pub fn wave_complete_message_ui(mut contexts: EguiContexts, wave: Res<Wave>) {
+pub fn wave_complete_message_ui(
+    mut contexts: EguiContexts,
+    wave: Res<Wave>,
+    tally: Option<Res<BonusTally>>,
+) {
     if wave.completion_timeout.paused() || wave.completion_timeout.finished() {
         return;
     }
 
+    let line = |ui: &mut egui::Ui, text: String, size: f32| {
+        ui.label(
+            RichText::new(text)
+                .font(FontId::proportional(size))
+                .color(Color32::WHITE),
+        );
+    };
     egui::Area::new("Wave Complete")
         .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., 0.))
         .show(contexts.ctx_mut(), |ui: &mut egui::Ui| {
-            ui.label(
-                RichText::new(format!(
-                    "Next wave in {:0>2}!",
-                    wave.completion_timeout.duration().as_secs()
-                        - wave.completion_timeout.elapsed_secs() as u64
-                ))
-                .font(FontId::proportional(24.))
-                .color(Color32::WHITE),
-            );
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                if let Some(tally) = tally {
+                    line(
+                        ui,
+                        format!("Cities {} x {}", tally.counted_cities, tally.city_points),
+                        18.,
+                    );
+                    line(
+                        ui,
+                        format!("Ammo {} x {}", tally.counted_ammo, tally.ammo_points),
+                        18.,
+                    );
+                    if tally.multiplier > 1 {
+                        line(ui, format!("{}x bonus", tally.multiplier), 12.);
+                    }
+                    if let Some(accuracy) = tally.accuracy {
+                        line(ui, format!("Accuracy {:.0}%", accuracy * 100.0), 12.);
+                    }
+                    line(ui, format!("Largest combo {}", tally.largest_combo), 12.);
+                    line(ui, format!("Bonus {:0>5}", tally.counted()), 24.);
+                }
+                line(
+                    ui,
+                    format!(
+                        "Next wave in {:0>2}!",
+                        wave.completion_timeout.duration().as_secs()
+                            - wave.completion_timeout.elapsed_secs() as u64
+                    ),
+                    24.,
+                );
+            });
         });
 }
 
 pub fn ammo_ui(
     mut contexts: EguiContexts,
     images: Res<ImageAssets>,
-    missile_ammo: Query<&MissileReserve, With<Player>>,
+    batteries: Query<(&Battery, &MissileReserve, &TankBody, &Health)>,
+    active: Option<Res<ActiveBattery>>,
 ) {
-    if let Ok(ammo) = missile_ammo.get_single() {
-        let ammo_id = contexts.add_image(images.missile.clone_weak());
-
-        egui::Area::new("Ammo")
-            .anchor(Align2::LEFT_TOP, egui::emath::vec2(10., 5.))
-            .show(contexts.ctx_mut(), |ui: &mut egui::Ui| {
-                ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+    let mut batteries: Vec<_> = batteries.iter().collect();
+    if batteries.is_empty() {
+        return;
+    }
+    batteries.sort_by_key(|(battery, _, _, _)| battery.index);
+    let ammo_id = contexts.add_image(images.missile.clone_weak());
+    let heart_full_id = contexts.add_image(images.heart_full.clone_weak());
+    let heart_empty_id = contexts.add_image(images.heart_empty.clone_weak());
+    let active = active.map(|active| active.0);
+    let several = batteries.len() > 1;
+
+    egui::Area::new("Ammo")
+        .anchor(Align2::LEFT_TOP, egui::emath::vec2(10., 5.))
+        .show(contexts.ctx_mut(), |ui: &mut egui::Ui| {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+                for (battery, ammo, tank, health) in batteries {
+                    let (text, color) = match tank {
+                        TankBody::Destroyed => ("--".to_string(), Color32::DARK_GRAY),
+                        TankBody::Intact if !battery.cooldown.finished() => {
+                            (format!("{:0>2}", ammo.0), Color32::GRAY)
+                        }
+                        TankBody::Intact if several && active == Some(battery.index) => {
+                            (format!("{:0>2}", ammo.0), Color32::YELLOW)
+                        }
+                        TankBody::Intact => (format!("{:0>2}", ammo.0), Color32::WHITE),
+                    };
                     ui.image(ammo_id, egui::emath::vec2(16., 16.));
                     ui.label(
-                        RichText::new(format!("{:0>2}", ammo.0))
+                        RichText::new(text)
                             .font(FontId::proportional(24.))
-                            .color(Color32::WHITE),
+                            .color(color),
                     );
-                });
+                    for heart in 0..health.max {
+                        let heart_id = if heart < health.current {
+                            heart_full_id
+                        } else {
+                            heart_empty_id
+                        };
+                        ui.image(heart_id, egui::emath::vec2(8., 8.));
+                    }
+                }
             });
-    }
+        });
 }
 
 pub fn wave_ui(mut contexts: EguiContexts, wave: Res<Wave>) {
@@ -921,15 +1655,53 @@ pub fn score_ui(mut contexts: EguiContexts, score: Res<Score>) {
         });
 }
 
-pub fn game_over_ui(mut contexts: EguiContexts) {
+// The modifiers of the run and what they make the final score worth
+pub fn modifiers_ui(mut contexts: EguiContexts, modifiers: Res<RunModifiers>) {
+    if modifiers.0.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = modifiers.0.iter().map(|modifier| modifier.name()).collect();
+    let percent = modifiers.score_percent();
+
+    egui::Area::new("Modifiers")
+        .anchor(Align2::CENTER_TOP, egui::emath::vec2(10., 35.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "{}  x{}.{:0>2}",
+                        names.join(", "),
+                        percent / 100,
+                        percent % 100
+                    ))
+                    .font(FontId::proportional(12.))
+                    .color(Color32::WHITE),
+                );
+            });
+        });
+}
+
+pub fn game_over_ui(mut contexts: EguiContexts, seed: Res<RunSeed>) {
     egui::Area::new("gameover")
         .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., 0.))
         .show(contexts.ctx_mut(), |ui| {
-            ui.label(
-                RichText::new("GAME OVER")
-                    .font(FontId::proportional(24.))
-                    .color(Color32::WHITE),
-            )
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new("GAME OVER")
+                        .font(FontId::proportional(24.))
+                        .color(Color32::WHITE),
+                );
+                ui.label(
+                    RichText::new(format!("Seed {}", seed.0))
+                        .font(FontId::proportional(12.))
+                        .color(Color32::WHITE),
+                );
+                ui.label(
+                    RichText::new("[R] Retry   [Esc] Menu")
+                        .font(FontId::proportional(12.))
+                        .color(Color32::WHITE),
+                );
+            })
         });
 }
 
@@ -948,40 +1720,93 @@ mod spawner {
     use crate::{
         game::{
             components::{
-                AnimationIndices, DropBombTimer, Enemy, Engulfable, Explodable, Foreground,
-                IdCounter, Missile, SpawnPoint, Ufo,
+                AnimationIndices, Bounding, DropBombTimer, Enemy, Engulfable, Explodable,
+                FlightPath, Flyer, Foreground, IdCounter, Mirv, Missile, SmartBomb, SpawnPoint,
+                FLYER_RADIUS, MISSILE_RADIUS,
             },
-            prelude::Stage,
+            prelude::{FlyerKind, FlyerParams, Stage},
         },
         SCREEN,
     };
 
     use super::color_from_vec;
 
-    pub fn ufo(commands: &mut Commands, rng: &mut RngComponent, images: Handle<TextureAtlas>) {
-        let origin_y = rng.i32(0..((SCREEN.y / 2.0) as i32 - 30)) as f32;
+    pub fn flyer(
+        commands: &mut Commands,
+        rng: &mut RngComponent,
+        images: Handle<TextureAtlas>,
+        flyer: &FlyerParams,
+    ) {
+        let [low, high] = flyer.altitude;
+        let origin_y = -SCREEN.y / 2.0 + 16.0 + low + rng.i32(0..(high - low) as i32) as f32;
         let sign = if rng.bool() { 1.0 } else { -1.0 };
         let origin_x = sign * (SCREEN.x / 2.0);
 
-        commands.spawn((
+        flyer_at(commands, images, flyer, vec2(origin_x, origin_y));
+    }
+
+    // the flyer crosses to the opposite side of the screen, straight or over a hump
+    pub fn flyer_at(
+        commands: &mut Commands,
+        images: Handle<TextureAtlas>,
+        flyer: &FlyerParams,
+        origin: Vec2,
+    ) {
+        let to = vec2(-origin.x, origin.y);
+        let path = match flyer.kind {
+            FlyerKind::Ufo | FlyerKind::Bomber => FlightPath::Straight(to),
+            FlyerKind::Satellite => FlightPath::Arc {
+                from: origin,
+                to,
+                height: flyer.arc_height,
+                progress: 0.0,
+            },
+        };
+        let sprite = match flyer.kind {
+            FlyerKind::Ufo => 8,
+            FlyerKind::Bomber => 14,
+            FlyerKind::Satellite => 15,
+        };
+        let mut entity = commands.spawn((
             SpriteSheetBundle {
                 texture_atlas: images,
-                sprite: TextureAtlasSprite::new(8),
-                transform: Transform::from_translation(Vec3::new(origin_x, origin_y, 1.0)),
+                sprite: TextureAtlasSprite {
+                    index: sprite,
+                    // bombers are drawn flying right
+                    flip_x: origin.x > 0.0,
+                    ..default()
+                },
+                transform: Transform::from_translation(origin.extend(1.0)),
                 ..default()
             },
-            AnimationIndices {
-                first: 8,
-                last: 11,
-                timer: Timer::from_seconds(0.2, TimerMode::Repeating),
+            Flyer {
+                kind: flyer.kind,
+                path,
+                speed: flyer.speed,
+                score: flyer.score,
+                turn_chance: flyer.turn_chance,
+                turn_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
             },
-            Ufo(vec2(-origin_x, origin_y)),
             Explodable,
             Engulfable,
+            Bounding(FLYER_RADIUS),
             Enemy,
             Foreground,
-            DropBombTimer(Timer::from_seconds(1.0, TimerMode::Repeating)),
         ));
+        if flyer.kind == FlyerKind::Ufo {
+            entity.insert(AnimationIndices {
+                first: 8,
+                last: 11,
+                timer: Timer::from_seconds(0.2, TimerMode::Repeating),
+            });
+        }
+        if flyer.bombs > 0 {
+            entity.insert(DropBombTimer {
+                timer: Timer::from_seconds(flyer.bomb_interval_secs, TimerMode::Repeating),
+                bombs: flyer.bombs,
+                chance: flyer.bomb_chance,
+            });
+        }
     }
 
     pub fn missile(
@@ -992,18 +1817,22 @@ mod spawner {
         stage: &Stage,
         wave: usize,
         origin: Option<Vec2>,
-    ) {
+        dest_x: Option<f32>,
+    ) -> Entity {
         let origin = origin.unwrap_or_else(|| {
             let x = rng.i32(-(SCREEN.x / 2.0) as i32..(SCREEN.x / 2.0) as i32) as f32;
             let y = SCREEN.y / 2.0;
             vec2(x, y)
         });
 
-        let sign = if rng.bool() { 1.0 } else { -1.0 };
-        let mut dest_x = sign * rng.usize(0..(SCREEN.x / 4.0) as usize) as f32;
-        if dest_x < -SCREEN.x || dest_x > SCREEN.x {
-            dest_x *= -1.0;
-        }
+        let dest_x = dest_x.unwrap_or_else(|| {
+            let sign = if rng.bool() { 1.0 } else { -1.0 };
+            let mut dest_x = sign * rng.usize(0..(SCREEN.x / 4.0) as usize) as f32;
+            if dest_x < -SCREEN.x || dest_x > SCREEN.x {
+                dest_x *= -1.0;
+            }
+            dest_x
+        });
         let parent = commands
             .spawn((
                 SpriteSheetBundle {
@@ -1019,6 +1848,7 @@ mod spawner {
                 },
                 Explodable,
                 Engulfable,
+                Bounding(MISSILE_RADIUS),
                 SpawnPoint(origin),
                 Enemy,
                 Foreground,
@@ -1035,7 +1865,52 @@ mod spawner {
                 },
                 ..default()
             },
-            Fill::color(color_from_vec(&stage.trail_cor)),
+            Fill::color(color_from_vec(stage.palette(wave).trail)),
+        ));
+
+        parent
+    }
+
+    // a missile of its own look that steers around explosions
+    pub fn smart_bomb(
+        commands: &mut Commands,
+        rng: &mut RngComponent,
+        id_counter: &mut ResMut<IdCounter>,
+        images: Handle<TextureAtlas>,
+        stage: &Stage,
+        wave: usize,
+        origin: Option<Vec2>,
+        dest_x: Option<f32>,
+    ) {
+        let bomb = missile(
+            commands, rng, id_counter, images, stage, wave, origin, dest_x,
+        );
+        commands
+            .entity(bomb)
+            .insert((SmartBomb, TextureAtlasSprite::new(12)));
+    }
+
+    // a missile of its own look that splits into warheads on the way down
+    pub fn mirv(
+        commands: &mut Commands,
+        rng: &mut RngComponent,
+        id_counter: &mut ResMut<IdCounter>,
+        images: Handle<TextureAtlas>,
+        stage: &Stage,
+        wave: usize,
+        origin: Option<Vec2>,
+        dest_x: Option<f32>,
+    ) {
+        let mirv = missile(
+            commands, rng, id_counter, images, stage, wave, origin, dest_x,
+        );
+        commands.entity(mirv).insert((
+            Mirv {
+                split_y: -SCREEN.y / 2.0 + 16.0 + stage.mirv_split_altitude,
+                warheads: stage.mirv_warheads(wave),
+                telegraphed: false,
+            },
+            TextureAtlasSprite::new(13),
         ));
     }
 }
@@ -0,0 +1,161 @@
+use std::{mem, time::Duration};
+
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
+
+use crate::GameState;
+
+use super::{
+    components::{Flyer, Missile, TankBody},
+    input::PlayerInput,
+    prelude::PauseState,
+};
+
+// The length of a tick, the game plays as many of them as fit in a frame
+pub const TICK: Duration = Duration::from_nanos(16_666_667);
+// How far the game catches up after a long frame, beyond that it slows down
+const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+/**
+ * Everything that moves, spawns or counts down in a run. Played once per
+ * tick, however many frames are drawn in between, so a run plays out the same
+ * at any frame rate.
+ */
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GameTick;
+
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TickSet {
+    // the positions of the last tick are put back before anything moves
+    Restore,
+    // the game plays, after the inputs of the tick are in
+    Play,
+    // the positions are kept to draw in between, the presses are used up
+    Store,
+}
+
+/**
+ * The clock of the game. Its own `Time` stands in for the clock of the frame
+ * while a tick is played, every tick is exactly `TICK` long.
+ */
+#[derive(Resource)]
+pub struct GameClock {
+    time: Time,
+    // frame time not played yet
+    behind: Duration,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        let mut time = Time::default();
+        time.update();
+        Self {
+            time,
+            behind: Duration::ZERO,
+        }
+    }
+}
+
+impl GameClock {
+    // how far the frame is between the last tick and the next one
+    fn alpha(&self) -> f32 {
+        self.behind.as_secs_f32() / TICK.as_secs_f32()
+    }
+}
+
+// Where an entity was after the last two ticks, it is drawn in between
+#[derive(Component)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+}
+
+pub trait TickEventExt {
+    // an event kept for two ticks rather than two frames, for the systems of a tick
+    fn add_tick_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl TickEventExt for App {
+    fn add_tick_event<T: Event>(&mut self) -> &mut Self {
+        self.init_resource::<Events<T>>().add_systems(
+            GameTick,
+            Events::<T>::update_system.in_set(TickSet::Restore),
+        )
+    }
+}
+
+// A change of state waits for the next frame, no tick is played before it
+fn state_pending(world: &World) -> bool {
+    world.resource::<NextState<GameState>>().0.is_some()
+        || world.resource::<NextState<PauseState>>().0.is_some()
+}
+
+/**
+ * Plays the ticks that fit in the time of the frame, with the clock of the
+ * game standing in for the clock of the frame
+ */
+pub fn play_ticks(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    world.resource_scope(|world, mut clock: Mut<GameClock>| {
+        clock.behind = (clock.behind + delta).min(MAX_CATCH_UP);
+        while clock.behind >= TICK && !state_pending(world) {
+            clock.behind -= TICK;
+            let next = clock.time.last_update().unwrap_or(clock.time.startup()) + TICK;
+            clock.time.update_with_instant(next);
+
+            let frame_time = mem::replace(&mut *world.resource_mut::<Time>(), clock.time.clone());
+            world.run_schedule(GameTick);
+            *world.resource_mut::<Time>() = frame_time;
+        }
+    });
+}
+
+// A stage starts on a whole tick
+pub fn reset_game_clock(mut clock: ResMut<GameClock>) {
+    clock.behind = Duration::ZERO;
+}
+
+pub fn restore_positions(mut positions: Query<(&mut Transform, &mut Interpolated)>) {
+    for (mut transform, mut interpolated) in positions.iter_mut() {
+        transform.translation = interpolated.current;
+        interpolated.previous = interpolated.current;
+    }
+}
+
+pub fn store_positions(
+    mut commands: Commands,
+    mut positions: Query<(&Transform, &mut Interpolated)>,
+    moving: Query<
+        (Entity, &Transform),
+        (
+            Without<Interpolated>,
+            Or<(With<Missile>, With<Flyer>, With<TankBody>)>,
+        ),
+    >,
+) {
+    for (transform, mut interpolated) in positions.iter_mut() {
+        interpolated.current = transform.translation;
+    }
+    for (entity, transform) in moving.iter() {
+        commands.entity(entity).insert(Interpolated {
+            previous: transform.translation,
+            current: transform.translation,
+        });
+    }
+}
+
+// Every press is played by a single tick
+pub fn release_presses(mut input: ResMut<PlayerInput>) {
+    input.fire = false;
+    input.battery = None;
+}
+
+// Draws what moves between the last two ticks, by how far the frame is along
+pub fn interpolate_positions(
+    clock: Res<GameClock>,
+    mut positions: Query<(&mut Transform, &Interpolated)>,
+) {
+    let alpha = clock.alpha();
+    for (mut transform, interpolated) in positions.iter_mut() {
+        transform.translation = interpolated.previous.lerp(interpolated.current, alpha);
+    }
+}
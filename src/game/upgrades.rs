@@ -0,0 +1,204 @@
+use bevy::{
+    asset::{AssetLoader, Error, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use super::{
+    components::{FLAME_RADIUS_STEPS, PLAYER_MISSILE_SPEED},
+    modifiers::RunModifiers,
+    power_ups::ActivePowerUps,
+    prelude::BatteryLayout,
+    schema::MAX_BATTERIES,
+};
+use crate::SCREEN;
+
+// How fast a battery is moved without upgrades
+pub const TANK_SPEED: f32 = 50.0;
+
+/**
+ * The upgrades sold in the shop between stages. Loaded from a
+ * `*.upgrades.json` file by the `UpgradesLoader`.
+ */
+#[derive(Deserialize, TypeUuid, TypePath)]
+#[uuid = "8f0c2a61-52d4-4c1e-9a37-64b5d2e0f7a9"]
+pub struct UpgradeList(pub Vec<Upgrade>);
+
+/**
+ * An upgrade as given in the upgrades file, e.g.
+ * `{ "name": "Tracks", "cost": 1000, "max_level": 2, "effect": { "tank_speed": 0.25 } }`
+ */
+#[derive(Deserialize, Clone, Debug)]
+pub struct Upgrade {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    // the price of the first level, every level after costs as much more
+    pub cost: usize,
+    #[serde(default = "one")]
+    pub max_level: usize,
+    pub effect: UpgradeEffect,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeEffect {
+    // more missiles for every battery
+    Ammo(u8),
+    // the missiles, flames and batteries of the player are this much faster or wider
+    MissileSpeed(f32),
+    FlameRadius(f32),
+    TankSpeed(f32),
+    // a destroyed city is rebuilt at the end of a wave
+    SpareCity,
+    // one more battery on every stage
+    ExtraBattery,
+}
+
+#[derive(Default)]
+pub struct UpgradesLoader;
+
+impl AssetLoader for UpgradesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let upgrades: UpgradeList = serde_json::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(upgrades));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["upgrades.json"]
+    }
+}
+
+/**
+ * What has been bought in the shop this run. The systems of the game read
+ * their numbers from here instead of the constants.
+ */
+#[derive(Resource, Default)]
+pub struct RunUpgrades {
+    // the levels bought of every upgrade, by its place in the list
+    pub levels: Vec<usize>,
+    // score spent in the shop, the score itself counts for the high scores
+    pub spent: usize,
+    pub ammo: u8,
+    pub missile_speed: f32,
+    pub flame_radius: f32,
+    pub tank_speed: f32,
+    pub spare_cities: usize,
+    pub extra_batteries: usize,
+}
+
+impl RunUpgrades {
+    pub fn level(&self, index: usize) -> usize {
+        self.levels.get(index).copied().unwrap_or(0)
+    }
+
+    // the price of the next level, none once every level is bought
+    pub fn price(&self, index: usize, upgrade: &Upgrade) -> Option<usize> {
+        let level = self.level(index);
+        (level < upgrade.max_level).then_some(upgrade.cost * (level + 1))
+    }
+
+    pub fn credits(&self, score: usize) -> usize {
+        score.saturating_sub(self.spent)
+    }
+
+    // buys the next level if there is one and the score is enough, true if it was bought
+    pub fn buy(&mut self, index: usize, upgrade: &Upgrade, score: usize) -> bool {
+        let Some(price) = self.price(index, upgrade) else {
+            return false;
+        };
+        if price > self.credits(score) {
+            return false;
+        }
+
+        if self.levels.len() <= index {
+            self.levels.resize(index + 1, 0);
+        }
+        self.levels[index] += 1;
+        self.spent += price;
+        match upgrade.effect {
+            UpgradeEffect::Ammo(ammo) => self.ammo = self.ammo.saturating_add(ammo),
+            UpgradeEffect::MissileSpeed(speed) => self.missile_speed += speed,
+            UpgradeEffect::FlameRadius(radius) => self.flame_radius += radius,
+            UpgradeEffect::TankSpeed(speed) => self.tank_speed += speed,
+            UpgradeEffect::SpareCity => self.spare_cities += 1,
+            UpgradeEffect::ExtraBattery => self.extra_batteries += 1,
+        }
+
+        true
+    }
+
+    pub fn missile_speed(&self) -> f32 {
+        PLAYER_MISSILE_SPEED * (1.0 + self.missile_speed)
+    }
+
+    pub fn tank_speed(&self) -> f32 {
+        TANK_SPEED * (1.0 + self.tank_speed)
+    }
+
+    // how much wider the flames of a shot are
+    pub fn flame_scale(&self) -> f32 {
+        1.0 + self.flame_radius
+    }
+
+    /**
+     * The batteries of a stage with the ammo bought, and the extra ones put in
+     * the widest gaps between them like the first battery of the stage
+     */
+    pub fn batteries(&self, stage: &[BatteryLayout]) -> Vec<BatteryLayout> {
+        let mut batteries = stage.to_vec();
+        let edge = SCREEN.x / 2.0 - 24.0;
+        let template = batteries.first().cloned().unwrap_or_default();
+        for _ in 0..self.extra_batteries {
+            if batteries.len() >= MAX_BATTERIES {
+                break;
+            }
+            let mut xs: Vec<f32> = batteries.iter().map(|battery| battery.x).collect();
+            xs.extend([-edge, edge]);
+            xs.sort_by(f32::total_cmp);
+            let gap = xs
+                .windows(2)
+                .max_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])))
+                .map_or(0.0, |pair| (pair[0] + pair[1]) / 2.0);
+            batteries.push(BatteryLayout {
+                x: gap,
+                ..template.clone()
+            });
+        }
+        for battery in batteries.iter_mut() {
+            battery.ammo = battery.ammo.saturating_add(self.ammo);
+        }
+
+        batteries
+    }
+}
+
+// How much wider the flames of a shot are, with upgrades, power-ups and modifiers
+pub fn shot_flame_scale(
+    power_ups: &ActivePowerUps,
+    upgrades: &RunUpgrades,
+    modifiers: &RunModifiers,
+) -> f32 {
+    power_ups.blast_scale() * upgrades.flame_scale() * modifiers.flame_scale()
+}
+
+// the flames of a shot, `scale` times as wide
+pub fn flame_steps(scale: f32) -> Vec<i32> {
+    FLAME_RADIUS_STEPS
+        .iter()
+        .map(|radius| (*radius as f32 * scale).round() as i32)
+        .collect()
+}
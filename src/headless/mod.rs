@@ -0,0 +1,198 @@
+use std::{process, str::FromStr};
+
+use bevy::{
+    input::InputPlugin,
+    log::{Level, LogPlugin},
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use bevy_turborand::prelude::RngPlugin;
+
+mod systems;
+use systems::*;
+
+use crate::{
+    autopilot::{Autopilot, AutopilotPlugin, Skill},
+    campaign::CampaignPlugin,
+    config::{Headless, ProgramConfig},
+    daily::Verification,
+    endless::EndlessPlugin,
+    enter_stage::{EnterStagePlugin, SkipStageIntro},
+    game::{
+        prelude::{GameMode, GameTick, InputSet, InputSource, RunModifiers, RunSeed, TICK},
+        GamePlugin,
+    },
+    replay::{Recording, Replay, ReplayPlugin},
+    GameState, ImageAssets,
+};
+
+// Number of waves simulated when `--waves` isn't given
+pub const DEFAULT_WAVES: usize = 10;
+
+/**
+ * Plays the game without a window, as fast as the machine allows, and
+ * prints a line of statistics for every wave. Made for tuning the
+ * difficulty of stages on machines without a GPU.
+ */
+pub fn run(cfg: ProgramConfig, seed: u64, replay: Option<Replay>) {
+    // a share code brings the seed and the inputs of its run
+    let verification = cfg.verify.as_deref().map(|code| {
+        Verification::load(code).unwrap_or_else(|err| {
+            println!("A problem occured when loading the share code: {err}");
+            process::exit(1);
+        })
+    });
+    let (seed, replay) = match &verification {
+        Some((verification, code)) => (
+            verification.challenge.seed,
+            Some(Replay {
+                seed: verification.challenge.seed,
+                ticks: code.ticks.clone(),
+                next: 0,
+            }),
+        ),
+        None => (seed, replay),
+    };
+
+    let policy = cfg
+        .policy
+        .as_deref()
+        .map_or(Ok(Policy::default()), Policy::from_str);
+    let skill = cfg
+        .skill
+        .as_deref()
+        .map_or(Ok(Skill::default()), Skill::from_str);
+    let (policy, skill) = policy
+        .and_then(|policy| Ok((policy, skill?)))
+        .unwrap_or_else(|err| {
+            println!("A problem occured when parsing args: {err}");
+            process::exit(1);
+        });
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        LogPlugin {
+            level: Level::WARN,
+            ..default()
+        },
+        AssetPlugin::default(),
+        InputPlugin,
+    ))
+    .add_state::<GameState>()
+    // nothing to load, the stages are loaded by the campaign
+    .insert_resource(NextState(Some(GameState::EnterGame)))
+    .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+    .insert_resource(Headless)
+    .insert_resource(SkipStageIntro)
+    .insert_resource(RunSeed(seed))
+    .insert_resource(placeholder_images())
+    .insert_resource(policy)
+    .insert_resource(Simulation {
+        stage: cfg.stage,
+        // a verified run goes on until it is overrun, like it did when played
+        waves: match verification {
+            Some(_) => usize::MAX,
+            None => cfg.waves.unwrap_or(DEFAULT_WAVES),
+        },
+    })
+    .add_plugins((
+        RngPlugin::new().with_rng_seed(seed),
+        CampaignPlugin,
+        EnterStagePlugin,
+        GamePlugin,
+        ReplayPlugin,
+        AutopilotPlugin,
+    ))
+    .add_systems(PreStartup, load_stage)
+    .add_systems(Startup, print_header)
+    .add_systems(Update, check_stage.run_if(in_state(GameState::EnterGame)))
+    .add_systems(
+        GameTick,
+        naive_policy.in_set(InputSet).run_if(
+            in_state(GameState::InGame)
+                .and_then(resource_equals(InputSource::Bot))
+                .and_then(resource_equals(Policy::Naive)),
+        ),
+    )
+    // after `Update`, while the stage of the completed wave is still current
+    .add_systems(Last, report_wave)
+    .add_systems(OnEnter(GameState::GameOver), report_game_over);
+
+    // a replay plays itself, the policy only decides runs without one
+    match replay {
+        Some(replay) => {
+            app.insert_resource(replay)
+                .insert_resource(InputSource::Replay);
+        }
+        None if policy == Policy::Autopilot => {
+            app.insert_resource(Autopilot::new(skill, seed))
+                .insert_resource(InputSource::Autopilot);
+        }
+        None => {
+            app.insert_resource(InputSource::Bot);
+        }
+    }
+    if let Some(path) = cfg.record {
+        app.insert_resource(Recording::new(path, seed));
+    }
+    // the daily challenge is an endless run with the modifiers of its day
+    if let Some((verification, _)) = verification {
+        app.add_plugins(EndlessPlugin)
+            .insert_resource(GameMode::Endless)
+            .insert_resource(RunModifiers(verification.challenge.modifiers.clone()))
+            .insert_resource(verification);
+    }
+
+    app.run();
+}
+
+#[derive(Resource)]
+pub struct Simulation {
+    // a single stage to play instead of the campaign
+    pub stage: Option<String>,
+    // the run ends after this many waves, or when the player is overrun
+    pub waves: usize,
+}
+
+// Who plays the simulated run
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Policy {
+    // never fires, shows how a stage plays out undefended
+    Idle,
+    // fires at the lowest enemy missile twice a second
+    Naive,
+    // leads its shots and guards the cities, see `autopilot`
+    #[default]
+    Autopilot,
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "idle" => Ok(Policy::Idle),
+            "naive" => Ok(Policy::Naive),
+            "autopilot" => Ok(Policy::Autopilot),
+            _ => Err(format!(
+                "unknown policy '{name}', expected idle, naive or autopilot"
+            )),
+        }
+    }
+}
+
+// Nothing is drawn, every sprite gets an empty handle
+fn placeholder_images() -> ImageAssets {
+    ImageAssets {
+        cursor: default(),
+        explosion: default(),
+        heart_full: default(),
+        heart_empty: default(),
+        cannon: default(),
+        tank: default(),
+        city: default(),
+        ground: default(),
+        missile: default(),
+    }
+}
@@ -0,0 +1,142 @@
+use std::process;
+
+use bevy::{app::AppExit, asset::LoadState, prelude::*};
+
+use crate::{
+    campaign::Campaign,
+    daily::Verification,
+    game::prelude::{
+        Enemy, Missile, PlayerInput, Score, Stage, StageHandle, Wave, WaveCompletedEvent, WaveStats,
+    },
+};
+
+use super::Simulation;
+
+const FIRE_INTERVAL_SECS: f32 = 0.5;
+
+pub fn load_stage(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(path) = &simulation.stage {
+        commands.insert_resource(Campaign {
+            stages: vec![asset_server.load(path.as_str())],
+            current: 0,
+        });
+    }
+}
+
+// A stage that can't be played would wait on its intro forever
+pub fn check_stage(
+    asset_server: Res<AssetServer>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+) {
+    if asset_server.get_load_state(&stage.0) == LoadState::Failed {
+        eprintln!("The stage could not be loaded");
+        process::exit(1);
+    }
+    if let Some(stage) = stages
+        .get(&stage.0)
+        .filter(|stage| !stage.problems.is_empty())
+    {
+        eprintln!("{} can't be played:", stage.name);
+        for problem in stage.problems.iter() {
+            eprintln!("  {problem}");
+        }
+        process::exit(1);
+    }
+}
+
+pub fn naive_policy(
+    mut input: ResMut<PlayerInput>,
+    mut cooldown: Local<f32>,
+    time: Res<Time>,
+    missiles: Query<&Transform, (With<Missile>, With<Enemy>)>,
+) {
+    *cooldown -= time.delta_seconds();
+    input.fire = false;
+
+    let lowest = missiles
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .min_by(|a, b| a.y.total_cmp(&b.y));
+    if let Some(target) = lowest {
+        input.cursor = target;
+        if *cooldown <= 0.0 {
+            input.fire = true;
+            *cooldown = FIRE_INTERVAL_SECS;
+        }
+    }
+}
+
+pub fn print_header() {
+    println!(
+        "stage,wave,result,missiles_spawned,missiles_destroyed,ufos_spawned,ufos_destroyed,\
+        shots_fired,shots_hit,largest_combo,cities_lost,score"
+    );
+}
+
+fn print_row(stage: Option<&Stage>, wave: usize, result: &str, stats: &WaveStats, score: usize) {
+    // keep the name from breaking the columns
+    let name = stage.map_or(String::new(), |stage| stage.name.replace(',', " "));
+    println!(
+        "{name},{},{result},{},{},{},{},{},{},{},{},{score}",
+        wave + 1,
+        stats.missiles_spawned,
+        stats.missiles_destroyed,
+        stats.ufos_spawned,
+        stats.ufos_destroyed,
+        stats.shots_fired,
+        stats.shots_hit,
+        stats.largest_combo,
+        stats.cities_lost,
+    );
+}
+
+pub fn report_wave(
+    mut wave_completed: EventReader<WaveCompletedEvent>,
+    mut exit: EventWriter<AppExit>,
+    simulation: Res<Simulation>,
+    score: Res<Score>,
+    stage: Option<Res<StageHandle>>,
+    stages: Res<Assets<Stage>>,
+) {
+    for WaveCompletedEvent { wave, stats } in wave_completed.iter() {
+        let stage = stage.as_ref().and_then(|stage| stages.get(&stage.0));
+        print_row(stage, *wave, "cleared", stats, score.0);
+
+        if wave + 1 >= simulation.waves {
+            exit.send(AppExit);
+        }
+    }
+}
+
+pub fn report_game_over(
+    mut exit: EventWriter<AppExit>,
+    stats: Res<WaveStats>,
+    wave: Res<Wave>,
+    score: Res<Score>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+    verification: Option<Res<Verification>>,
+) {
+    print_row(stages.get(&stage.0), wave.n, "overrun", &stats, score.0);
+    if let Some(verification) = verification {
+        let date = verification.challenge.date();
+        if !verification.matches(&score, wave.n + 1) {
+            eprintln!(
+                "The share code of {date} claims {} points on wave {}, its inputs play out to {} \
+                on wave {}",
+                verification.score,
+                verification.wave,
+                score.0,
+                wave.n + 1
+            );
+            process::exit(1);
+        }
+        println!("The share code of {date} is verified");
+    }
+    exit.send(AppExit);
+}
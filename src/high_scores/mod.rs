@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+mod systems;
+use systems::*;
+
+use crate::{
+    daily::DailyRun,
+    data_file,
+    game::prelude::{GameMode, InputSource},
+    GameState,
+};
+
+// Number of runs kept in each table
+pub const MAX_HIGH_SCORES: usize = 10;
+const HIGH_SCORES_FILE: &str = "high_scores.json";
+const ENDLESS_HIGH_SCORES_FILE: &str = "endless_high_scores.json";
+
+// Keeps the best runs in the user's data directory, asks for initials when a
+// run makes it into the table and shows the tables, opened from the main menu
+pub struct HighScoresPlugin;
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShownTable>()
+            .add_systems(Startup, load_high_scores)
+            // only runs played on the devices can make it into the table, the
+            // daily challenge keeps its own log
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                check_high_score.run_if(
+                    resource_equals(InputSource::Devices)
+                        .and_then(not(resource_exists::<DailyRun>())),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    (initials_keys, initials_ui).run_if(resource_exists::<InitialsEntry>()),
+                    new_high_score_ui.run_if(resource_exists::<NewHighScore>()),
+                )
+                    .run_if(in_state(GameState::GameOver)),
+            )
+            .add_systems(OnExit(GameState::GameOver), teardown_entry)
+            .add_systems(
+                Update,
+                (switch_table, high_scores_ui, close_high_scores)
+                    .chain()
+                    .run_if(in_state(GameState::HighScores)),
+            );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighScore {
+    pub initials: String,
+    pub score: usize,
+    // the wave the run ended on, counted from 1
+    pub wave: usize,
+    pub stage: String,
+    pub seed: u64,
+    // YYYY-MM-DD, empty where the clock can't be read
+    pub date: String,
+}
+
+#[derive(Default)]
+pub struct HighScores {
+    // best first
+    pub entries: Vec<HighScore>,
+    // where the table is saved, none where there is no file system (wasm)
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    pub fn load(file_name: &str) -> Self {
+        let path = data_file::path(file_name);
+        let entries = data_file::load(path.as_deref(), "high scores");
+
+        Self { entries, path }
+    }
+
+    pub fn save(&self) {
+        data_file::save(self.path.as_deref(), &self.entries, "high scores");
+    }
+
+    pub fn qualifies(&self, score: usize) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_HIGH_SCORES
+                || self.entries.iter().any(|entry| entry.score < score))
+    }
+
+    // Adds a run to the table and returns its place, counted from 0
+    pub fn insert(&mut self, high_score: HighScore) -> usize {
+        // a tie goes to the run that got there first
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| entry.score < high_score.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, high_score);
+        self.entries.truncate(MAX_HIGH_SCORES);
+
+        rank
+    }
+}
+
+/**
+ * A table for every mode, endless runs go on far longer than the campaign and
+ * would crowd its runs out
+ */
+#[derive(Resource)]
+pub struct Leaderboards {
+    pub campaign: HighScores,
+    pub endless: HighScores,
+}
+
+impl Leaderboards {
+    pub fn table(&self, mode: GameMode) -> &HighScores {
+        match mode {
+            GameMode::Campaign => &self.campaign,
+            GameMode::Endless => &self.endless,
+        }
+    }
+
+    pub fn table_mut(&mut self, mode: GameMode) -> &mut HighScores {
+        match mode {
+            GameMode::Campaign => &mut self.campaign,
+            GameMode::Endless => &mut self.endless,
+        }
+    }
+}
+
+// The table shown on the high scores screen
+#[derive(Resource, Default)]
+pub struct ShownTable(pub GameMode);
+
+// The initials of a run that made it into the table, while they are typed
+#[derive(Resource)]
+pub struct InitialsEntry {
+    pub letters: [u8; 3],
+    pub cursor: usize,
+}
+
+impl Default for InitialsEntry {
+    fn default() -> Self {
+        Self {
+            letters: [b'A'; 3],
+            cursor: 0,
+        }
+    }
+}
+
+impl InitialsEntry {
+    pub fn initials(&self) -> String {
+        String::from_utf8_lossy(&self.letters).to_string()
+    }
+}
+
+// The place of the run that just made it into the table
+#[derive(Resource)]
+pub struct NewHighScore(pub usize);
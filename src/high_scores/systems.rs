@@ -0,0 +1,264 @@
+use bevy::{prelude::*, window::ReceivedCharacter};
+use bevy_egui::{
+    egui::{self, Align2, Color32, FontId, RichText},
+    EguiContexts,
+};
+
+use crate::{
+    date,
+    game::prelude::{GameMode, RunSeed, Score, Stage, StageHandle, Wave},
+    GameState,
+};
+
+use super::{
+    HighScore, HighScores, InitialsEntry, Leaderboards, NewHighScore, ShownTable,
+    ENDLESS_HIGH_SCORES_FILE, HIGH_SCORES_FILE,
+};
+
+pub fn load_high_scores(mut commands: Commands) {
+    commands.insert_resource(Leaderboards {
+        campaign: HighScores::load(HIGH_SCORES_FILE),
+        endless: HighScores::load(ENDLESS_HIGH_SCORES_FILE),
+    });
+}
+
+pub fn check_high_score(
+    mut commands: Commands,
+    leaderboards: Res<Leaderboards>,
+    mode: Res<GameMode>,
+    score: Res<Score>,
+) {
+    if leaderboards.table(*mode).qualifies(score.0) {
+        commands.insert_resource(InitialsEntry::default());
+    }
+}
+
+/**
+ * Letters are typed or picked with up and down, left and right move between
+ * them and enter saves the run
+ */
+pub fn initials_keys(
+    mut commands: Commands,
+    mut entry: ResMut<InitialsEntry>,
+    mut typed: EventReader<ReceivedCharacter>,
+    keyboard: Res<Input<KeyCode>>,
+    mut leaderboards: ResMut<Leaderboards>,
+    mode: Res<GameMode>,
+    mut shown: ResMut<ShownTable>,
+    score: Res<Score>,
+    wave: Res<Wave>,
+    seed: Res<RunSeed>,
+    stage: Res<StageHandle>,
+    stages: Res<Assets<Stage>>,
+) {
+    for ReceivedCharacter { char, .. } in typed.iter() {
+        if char.is_ascii_alphabetic() {
+            let cursor = entry.cursor;
+            entry.letters[cursor] = char.to_ascii_uppercase() as u8;
+            entry.cursor = (cursor + 1).min(2);
+        }
+    }
+
+    let cursor = entry.cursor;
+
+    if keyboard.just_pressed(KeyCode::Up) {
+        entry.letters[cursor] = if entry.letters[cursor] == b'Z' {
+            b'A'
+        } else {
+            entry.letters[cursor] + 1
+        };
+    }
+    if keyboard.just_pressed(KeyCode::Down) {
+        entry.letters[cursor] = if entry.letters[cursor] == b'A' {
+            b'Z'
+        } else {
+            entry.letters[cursor] - 1
+        };
+    }
+    if keyboard.any_just_pressed([KeyCode::Left, KeyCode::Back]) {
+        entry.cursor = entry.cursor.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::Right) {
+        entry.cursor = (entry.cursor + 1).min(2);
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) {
+        let high_scores = leaderboards.table_mut(*mode);
+        let rank = high_scores.insert(HighScore {
+            initials: entry.initials(),
+            score: score.0,
+            wave: wave.n + 1,
+            stage: stages
+                .get(&stage.0)
+                .map_or(String::new(), |stage| stage.name.clone()),
+            seed: seed.0,
+            date: date::today(),
+        });
+        high_scores.save();
+        // the table the run made it into is the one shown next
+        shown.0 = *mode;
+        commands.remove_resource::<InitialsEntry>();
+        commands.insert_resource(NewHighScore(rank));
+    }
+}
+
+pub fn initials_ui(mut contexts: EguiContexts, entry: Res<InitialsEntry>) {
+    egui::Area::new("initials")
+        .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., 60.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new("NEW HIGH SCORE!")
+                        .font(FontId::proportional(18.))
+                        .color(Color32::WHITE),
+                );
+                ui.horizontal(|ui| {
+                    for (i, letter) in entry.letters.iter().enumerate() {
+                        let color = if i == entry.cursor {
+                            Color32::YELLOW
+                        } else {
+                            Color32::WHITE
+                        };
+                        ui.label(
+                            RichText::new((*letter as char).to_string())
+                                .font(FontId::proportional(24.))
+                                .color(color),
+                        );
+                    }
+                });
+                ui.label(
+                    RichText::new("Type your initials, [Enter] to save")
+                        .font(FontId::proportional(12.))
+                        .color(Color32::WHITE),
+                );
+            })
+        });
+}
+
+pub fn new_high_score_ui(
+    mut contexts: EguiContexts,
+    new_high_score: Res<NewHighScore>,
+    leaderboards: Res<Leaderboards>,
+    mode: Res<GameMode>,
+) {
+    let Some(entry) = leaderboards.table(*mode).entries.get(new_high_score.0) else {
+        return;
+    };
+
+    egui::Area::new("new high score")
+        .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., 60.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                RichText::new(format!(
+                    "#{} {} {:0>7}",
+                    new_high_score.0 + 1,
+                    entry.initials,
+                    entry.score
+                ))
+                .font(FontId::proportional(18.))
+                .color(Color32::YELLOW),
+            );
+        });
+}
+
+pub fn teardown_entry(mut commands: Commands) {
+    commands.remove_resource::<InitialsEntry>();
+    commands.remove_resource::<NewHighScore>();
+}
+
+pub fn close_high_scores(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+) {
+    let pad_back = gamepads.iter().any(|pad| {
+        pad_buttons.any_just_pressed([
+            GamepadButton::new(pad, GamepadButtonType::East),
+            GamepadButton::new(pad, GamepadButtonType::South),
+        ])
+    });
+    if keyboard.any_just_released([KeyCode::Escape, KeyCode::Space, KeyCode::Return]) || pad_back {
+        // the menu would take the same release as picking an entry
+        keyboard.clear_just_released(KeyCode::Space);
+        keyboard.clear_just_released(KeyCode::Return);
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+// left and right flip between the campaign and the endless table
+pub fn switch_table(
+    mut shown: ResMut<ShownTable>,
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+) {
+    let pad_switch = gamepads.iter().any(|pad| {
+        pad_buttons.any_just_pressed([
+            GamepadButton::new(pad, GamepadButtonType::DPadLeft),
+            GamepadButton::new(pad, GamepadButtonType::DPadRight),
+        ])
+    });
+    if keyboard.any_just_pressed([KeyCode::Left, KeyCode::Right, KeyCode::A, KeyCode::D])
+        || pad_switch
+    {
+        shown.0 = match shown.0 {
+            GameMode::Campaign => GameMode::Endless,
+            GameMode::Endless => GameMode::Campaign,
+        };
+    }
+}
+
+pub fn high_scores_ui(
+    mut contexts: EguiContexts,
+    leaderboards: Res<Leaderboards>,
+    shown: Res<ShownTable>,
+) {
+    let high_scores = leaderboards.table(shown.0);
+    let title = match shown.0 {
+        GameMode::Campaign => "HIGH SCORES - CAMPAIGN",
+        GameMode::Endless => "HIGH SCORES - ENDLESS",
+    };
+    egui::Area::new("high scores")
+        .anchor(Align2::CENTER_TOP, egui::emath::vec2(0., 20.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new(title)
+                        .font(FontId::proportional(24.))
+                        .color(Color32::WHITE),
+                );
+                if high_scores.entries.is_empty() {
+                    ui.label(
+                        RichText::new("No runs yet")
+                            .font(FontId::proportional(12.))
+                            .color(Color32::WHITE),
+                    );
+                }
+                egui::Grid::new("high score table").show(ui, |ui| {
+                    for (i, entry) in high_scores.entries.iter().enumerate() {
+                        for cell in [
+                            format!("{}.", i + 1),
+                            entry.initials.clone(),
+                            format!("{:0>7}", entry.score),
+                            format!("wave {}", entry.wave),
+                            entry.stage.clone(),
+                            entry.date.clone(),
+                        ] {
+                            ui.label(
+                                RichText::new(cell)
+                                    .font(FontId::proportional(12.))
+                                    .color(Color32::WHITE),
+                            );
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.label(
+                    RichText::new("[Left] / [Right] to switch tables, [Esc] to go back")
+                        .font(FontId::proportional(12.))
+                        .color(Color32::WHITE),
+                );
+            })
+        });
+}
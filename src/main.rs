@@ -8,24 +8,46 @@ use bevy::{
     DefaultPlugins,
 };
 use bevy_asset_loader::prelude::{AssetCollection, LoadingState, LoadingStateAppExt};
-use bevy_common_assets::json::JsonAssetPlugin;
 use bevy_egui::EguiSettings;
 use bevy_egui::{
     egui::{FontData, FontDefinitions, FontFamily},
     EguiContexts, EguiPlugin,
 };
 // use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use autopilot::AutopilotPlugin;
 use bevy_turborand::prelude::RngPlugin;
+use campaign::CampaignPlugin;
 use config::Debug;
-use enter_stage::EnterStagePlugin;
-use game::{prelude::*, GamePlugin};
+use daily::DailyPlugin;
+use endless::EndlessPlugin;
+use enter_stage::{EnterStagePlugin, SkipStageIntro};
+use game::{
+    prelude::{random_seed, FixedSeed, InputSource, RunSeed},
+    GamePlugin,
+};
+use high_scores::HighScoresPlugin;
 use main_menu::*;
+use pause::PausePlugin;
+use replay::{Recording, Replay, ReplayPlugin};
+use shop::ShopPlugin;
 use std::{env, process, time::Duration};
 
+mod autopilot;
+mod campaign;
 mod config;
+mod daily;
+mod data_file;
+mod date;
+mod endless;
 mod enter_stage;
 mod game;
+mod headless;
+mod high_scores;
 mod main_menu;
+mod menu;
+mod pause;
+mod replay;
+mod shop;
 
 pub const SCREEN: Vec2 = Vec2::from_array([495.0, 270.0]);
 pub const DARK: Color = Color::rgb(0.191, 0.184, 0.156);
@@ -33,7 +55,7 @@ pub const LIGHT: Color = Color::rgb(0.852, 0.844, 0.816);
 
 #[derive(AssetCollection, Resource)]
 pub struct ImageAssets {
-    #[asset(texture_atlas(tile_size_x = 16.0, tile_size_y = 16.0, columns = 8, rows = 2))]
+    #[asset(texture_atlas(tile_size_x = 16.0, tile_size_y = 16.0, columns = 8, rows = 3))]
     #[asset(path = "textures/cursor.png")]
     pub cursor: Handle<TextureAtlas>,
     #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 4, rows = 1))]
@@ -49,7 +71,7 @@ pub struct ImageAssets {
     #[asset(texture_atlas(tile_size_x = 48.0, tile_size_y = 32.0, columns = 3, rows = 1))]
     #[asset(path = "textures/tank.png")]
     pub tank: Handle<TextureAtlas>,
-    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 2, rows = 1))]
+    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 3, rows = 1))]
     #[asset(path = "textures/city.png")]
     pub city: Handle<TextureAtlas>,
     #[asset(texture_atlas(tile_size_x = 495.0, tile_size_y = 32.0, columns = 1, rows = 1))]
@@ -67,7 +89,10 @@ pub enum GameState {
     EnterGame,
     InGame,
     GameOver,
+    // spending the score on upgrades between two stages
+    Shop,
     LeaveGame,
+    HighScores,
 }
 
 /**
@@ -80,6 +105,28 @@ fn main() {
         println!("A problem occured when parsing args: {err}");
         process::exit(1);
     });
+    let replay = cfg.replay.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|err| {
+            println!("A problem occured when loading the replay: {err}");
+            process::exit(1);
+        })
+    });
+    let seed = replay
+        .as_ref()
+        .map(|replay| replay.seed)
+        .or(cfg.seed)
+        .unwrap_or_else(random_seed);
+    if cfg.headless {
+        headless::run(cfg, seed, replay);
+        return;
+    }
+    // recorded runs start right away, so the menu can't change how they play out
+    let recorded = cfg.record.is_some() || replay.is_some();
+    let first_state = if recorded {
+        GameState::EnterGame
+    } else {
+        GameState::MainMenu
+    };
 
     let mut app = App::new();
     app.add_plugins(
@@ -110,26 +157,44 @@ fn main() {
             }),
     )
     .add_state::<GameState>()
-    .add_loading_state(
-        LoadingState::new(GameState::AssetLoading).continue_to_state(GameState::EnterGame),
-    )
+    .add_loading_state(LoadingState::new(GameState::AssetLoading).continue_to_state(first_state))
     .insert_resource(Debug(cfg.debug))
+    .insert_resource(RunSeed(seed))
     .add_collection_to_loading_state::<_, ImageAssets>(GameState::AssetLoading)
     .add_plugins(FrameTimeDiagnosticsPlugin::default())
     // .add_plugins(
     //     WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::Escape)),
     // )
     .add_plugins((
-        RngPlugin::new(), /* .with_rng_seed(220718) */
-        JsonAssetPlugin::<Stage>::new(&["stage.json"]),
+        RngPlugin::new().with_rng_seed(seed),
         EguiPlugin,
         MainMenuPlugin,
+        CampaignPlugin,
         EnterStagePlugin,
         GamePlugin,
+        ReplayPlugin,
+        AutopilotPlugin,
+        HighScoresPlugin,
+        PausePlugin,
+        ShopPlugin,
+        EndlessPlugin,
+        DailyPlugin,
     ))
     .add_systems(Startup, (setup_fonts, spawn_camera))
     .add_systems(Update, window_resized);
 
+    if cfg.seed.is_some() || replay.is_some() {
+        app.insert_resource(FixedSeed);
+    }
+    if let Some(replay) = replay {
+        app.insert_resource(replay)
+            .insert_resource(InputSource::Replay)
+            .insert_resource(SkipStageIntro);
+    }
+    if let Some(path) = cfg.record {
+        app.insert_resource(Recording::new(path, seed));
+    }
+
     app.run();
 }
 
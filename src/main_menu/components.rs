@@ -1,4 +1,14 @@
 use bevy::prelude::Component;
 
+use super::MenuAction;
+
 #[derive(Component)]
 pub struct MainMenuText;
+#[derive(Component)]
+pub struct MenuTitle;
+// Holds the entries of the current page
+#[derive(Component)]
+pub struct MenuList;
+// An entry of the menu and its place on the page
+#[derive(Component)]
+pub struct MenuItem(pub MenuAction, pub usize);
@@ -1,20 +1,82 @@
-use self::systems::{setup, teardown, transition_to_game};
-use crate::GameState;
+use self::systems::{
+    apply_options, menu_actions, menu_keys, menu_mouse, menu_theme, setup, spawn_menu_items,
+    teardown,
+};
+use crate::{
+    game::prelude::{Modifier, RunModifiers},
+    GameState,
+};
 use bevy::prelude::*;
 mod components;
 mod systems;
 /**
- * sett state for lukking
- * hello world fader ut
+ * The title screen, where runs are started from. Played with the keyboard,
+ * the mouse or a gamepad.
  */
 pub struct MainMenuPlugin;
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::MainMenu), setup)
+        app.add_event::<MenuActionEvent>()
+            .init_resource::<Options>()
+            .add_systems(OnEnter(GameState::MainMenu), setup)
             .add_systems(
                 Update,
-                transition_to_game.run_if(in_state(GameState::MainMenu)),
+                (
+                    spawn_menu_items.run_if(resource_changed::<MenuPage>()),
+                    menu_keys,
+                    menu_mouse,
+                    menu_actions,
+                    menu_theme,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::MainMenu)),
             )
+            .add_systems(Update, apply_options.run_if(resource_changed::<Options>()))
             .add_systems(OnExit(GameState::MainMenu), teardown);
     }
 }
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuAction {
+    StartCampaign,
+    Endless,
+    // today's daily challenge, scored the first time only
+    DailyChallenge,
+    StageSelect,
+    Modifiers,
+    // the autopilot plays the campaign until a key is pressed
+    Demo,
+    HighScores,
+    Options,
+    Quit,
+    // play a single stage of the campaign, by index
+    Stage(usize),
+    ToggleFullscreen,
+    // turn a modifier of the next runs on or off
+    ToggleModifier(Modifier),
+    Back,
+}
+
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum MenuPage {
+    #[default]
+    Main,
+    Stages,
+    Modifiers,
+    Options,
+}
+
+// The highlighted entry of the current page
+#[derive(Resource, Default)]
+pub struct MenuSelection(pub usize);
+
+#[derive(Event)]
+pub struct MenuActionEvent(pub MenuAction);
+
+// Settings changed from the menu
+#[derive(Resource, Default)]
+pub struct Options {
+    pub fullscreen: bool,
+    // picked for every run started from the menu, but the daily challenge
+    pub modifiers: RunModifiers,
+}
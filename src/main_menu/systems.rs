@@ -1,69 +1,445 @@
-use bevy::prelude::*;
+use bevy::{
+    app::AppExit,
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
 
-use crate::GameState;
+use crate::{
+    autopilot::Demo,
+    campaign::Campaign,
+    daily::{DailyChallenge, DailyLog},
+    game::prelude::{color_from_vec, reset_run, GameMode, InputSource, Modifier, RunSeed, Stage},
+    menu::{pad_pressed, set_cursor_visible},
+    replay::Replay,
+    GameState, DARK, LIGHT,
+};
 
-use super::components::MainMenuText;
+use super::{
+    components::{MainMenuText, MenuItem, MenuList, MenuTitle},
+    MenuAction, MenuActionEvent, MenuPage, MenuSelection, Options,
+};
 
-pub fn transition_to_game(
-    mut next_state: ResMut<NextState<GameState>>,
+// how far the stick has to be pushed to move the selection
+const STICK_THRESHOLD: f32 = 0.5;
+
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut windows: Query<&mut Window>,
+) {
+    commands.insert_resource(MenuPage::Main);
+    commands.insert_resource(MenuSelection(0));
+    set_cursor_visible(&mut windows, true);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            MainMenuText,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                TextBundle::from_sections([TextSection::new(
+                    "INVASION",
+                    TextStyle {
+                        font: asset_server.load("fonts/visitor.ttf"),
+                        font_size: 40.0,
+                        color: Color::WHITE,
+                    },
+                )])
+                .with_style(Style {
+                    margin: UiRect {
+                        top: Val::Px(32.0),
+                        bottom: Val::Px(16.0),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                MenuTitle,
+            ));
+            root.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                MenuList,
+            ));
+        });
+}
+
+fn page_actions(page: MenuPage, stages: usize) -> Vec<MenuAction> {
+    match page {
+        MenuPage::Main => vec![
+            MenuAction::StartCampaign,
+            MenuAction::Endless,
+            MenuAction::DailyChallenge,
+            MenuAction::StageSelect,
+            MenuAction::Modifiers,
+            MenuAction::Demo,
+            MenuAction::HighScores,
+            MenuAction::Options,
+            MenuAction::Quit,
+        ],
+        MenuPage::Stages => (0..stages)
+            .map(MenuAction::Stage)
+            .chain([MenuAction::Back])
+            .collect(),
+        MenuPage::Modifiers => Modifier::ALL
+            .into_iter()
+            .map(MenuAction::ToggleModifier)
+            .chain([MenuAction::Back])
+            .collect(),
+        MenuPage::Options => vec![MenuAction::ToggleFullscreen, MenuAction::Back],
+    }
+}
+
+pub fn spawn_menu_items(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    page: Res<MenuPage>,
+    campaign: Res<Campaign>,
+    list: Query<Entity, With<MenuList>>,
+) {
+    let Ok(list) = list.get_single() else {
+        return;
+    };
+
+    commands
+        .entity(list)
+        .despawn_descendants()
+        .with_children(|list| {
+            for (i, action) in page_actions(*page, campaign.stages.len())
+                .into_iter()
+                .enumerate()
+            {
+                list.spawn((
+                    ButtonBundle {
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    },
+                    MenuItem(action, i),
+                ))
+                .with_children(|item| {
+                    // the label is filled in by `menu_theme`
+                    item.spawn(TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/visitor.ttf"),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+            }
+        });
+}
+
+pub fn menu_keys(
     mut keyboard: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    pad_axes: Res<Axis<GamepadAxis>>,
+    mut stick_held: Local<bool>,
+    mut selection: ResMut<MenuSelection>,
+    page: Res<MenuPage>,
+    items: Query<&MenuItem>,
+    mut actions: EventWriter<MenuActionEvent>,
 ) {
-    if keyboard.just_released(KeyCode::Space) {
-        keyboard.clear_just_released(KeyCode::Space);
-        next_state.set(GameState::EnterGame);
+    let pad = |kind| pad_pressed(&gamepads, &pad_buttons, kind);
+    let stick = gamepads
+        .iter()
+        .filter_map(|pad| pad_axes.get(GamepadAxis::new(pad, GamepadAxisType::LeftStickY)))
+        .find(|y| y.abs() > STICK_THRESHOLD);
+    // a push of the stick moves the selection once
+    let (stick_up, stick_down) = match stick {
+        Some(y) if !*stick_held => (y > 0.0, y < 0.0),
+        _ => (false, false),
+    };
+    *stick_held = stick.is_some();
+
+    let count = items.iter().len();
+    if count == 0 {
+        return;
+    }
+    if keyboard.any_just_pressed([KeyCode::Up, KeyCode::W])
+        || pad(GamepadButtonType::DPadUp)
+        || stick_up
+    {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    if keyboard.any_just_pressed([KeyCode::Down, KeyCode::S])
+        || pad(GamepadButtonType::DPadDown)
+        || stick_down
+    {
+        selection.0 = (selection.0 + 1) % count;
+    }
+
+    // on release, so the stage intro doesn't see the same key
+    let activated = keyboard.any_just_released([KeyCode::Return, KeyCode::Space]);
+    keyboard.clear_just_released(KeyCode::Return);
+    keyboard.clear_just_released(KeyCode::Space);
+    if activated || pad(GamepadButtonType::South) {
+        if let Some(item) = items.iter().find(|item| item.1 == selection.0) {
+            actions.send(MenuActionEvent(item.0));
+        }
+    }
+    if *page != MenuPage::Main
+        && (keyboard.just_pressed(KeyCode::Escape) || pad(GamepadButtonType::East))
+    {
+        actions.send(MenuActionEvent(MenuAction::Back));
     }
 }
 
-pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Text with multiple sections
-    commands.spawn((
-        // Create a TextBundle that has a Text with a list of sections.
-        TextBundle::from_sections([TextSection::new(
-            "INVASION",
-            TextStyle {
-                font: asset_server.load("fonts/visitor.ttf"),
-                font_size: 40.0,
-                color: Color::WHITE,
-            },
-        )])
-        .with_style(Style {
-            margin: UiRect {
-                top: Val::Px(32.0),
-                right: Val::Auto,
-                left: Val::Auto,
-                ..default()
-            },
+pub fn menu_mouse(
+    items: Query<(&Interaction, &MenuItem), Changed<Interaction>>,
+    mut selection: ResMut<MenuSelection>,
+    mut actions: EventWriter<MenuActionEvent>,
+) {
+    for (interaction, item) in items.iter() {
+        match interaction {
+            Interaction::Hovered => selection.0 = item.1,
+            Interaction::Pressed => {
+                selection.0 = item.1;
+                actions.send(MenuActionEvent(item.0));
+            }
+            Interaction::None => {}
+        }
+    }
+}
 
-            ..default()
-        }),
-        MainMenuText,
-    ));
-    commands.spawn((
-        // Create a TextBundle that has a Text with a list of sections.
-        TextBundle::from_sections([TextSection::new(
-            "Press [Space] to begin",
-            TextStyle {
-                font: asset_server.load("fonts/visitor.ttf"),
-                font_size: 24.0,
-                color: Color::WHITE,
-            },
-        )])
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Percent(30.0),
-            margin: UiRect {
-                left: Val::Auto,
-                right: Val::Auto,
-                ..default()
-            },
-            ..default()
-        }),
-        MainMenuText,
-    ));
+pub fn menu_actions(
+    mut commands: Commands,
+    mut actions: EventReader<MenuActionEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut page: ResMut<MenuPage>,
+    mut selection: ResMut<MenuSelection>,
+    mut campaign: ResMut<Campaign>,
+    mut options: ResMut<Options>,
+    mut daily: ResMut<DailyLog>,
+    seed: Res<RunSeed>,
+    replay: Option<Res<Replay>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let modifiers = options.modifiers.clone();
+    let replaying = replay.is_some();
+    let start = |commands: &mut Commands, mode: GameMode| {
+        // runs from the menu are played by the player, after a `--replay` too
+        if replaying {
+            Replay::end(commands);
+        }
+        commands.insert_resource(InputSource::Devices);
+        reset_run(commands);
+        commands.insert_resource(mode);
+        commands.insert_resource(modifiers.clone());
+    };
+
+    for MenuActionEvent(action) in actions.iter() {
+        match *action {
+            MenuAction::StartCampaign => {
+                start(&mut commands, GameMode::Campaign);
+                campaign.current = 0;
+                next_state.set(GameState::EnterGame);
+            }
+            MenuAction::Endless => {
+                start(&mut commands, GameMode::Endless);
+                campaign.current = 0;
+                next_state.set(GameState::EnterGame);
+            }
+            MenuAction::DailyChallenge => {
+                let Some(challenge) = DailyChallenge::today() else {
+                    warn!("The daily challenge needs the date, which can't be read here");
+                    continue;
+                };
+                start(&mut commands, GameMode::Endless);
+                daily.start(&mut commands, challenge);
+                campaign.current = 0;
+                next_state.set(GameState::EnterGame);
+            }
+            MenuAction::Stage(i) => {
+                start(&mut commands, GameMode::Campaign);
+                campaign.current = i;
+                next_state.set(GameState::EnterGame);
+            }
+            MenuAction::StageSelect => {
+                *page = MenuPage::Stages;
+                selection.0 = 0;
+            }
+            MenuAction::Modifiers => {
+                *page = MenuPage::Modifiers;
+                selection.0 = 0;
+            }
+            MenuAction::Options => {
+                *page = MenuPage::Options;
+                selection.0 = 0;
+            }
+            MenuAction::Back => {
+                *page = MenuPage::Main;
+                selection.0 = 0;
+            }
+            MenuAction::Demo => {
+                if replaying {
+                    Replay::end(&mut commands);
+                }
+                Demo::start(&mut commands, seed.0);
+                campaign.current = 0;
+                next_state.set(GameState::EnterGame);
+            }
+            MenuAction::HighScores => next_state.set(GameState::HighScores),
+            MenuAction::ToggleFullscreen => options.fullscreen = !options.fullscreen,
+            MenuAction::ToggleModifier(modifier) => options.modifiers.toggle(modifier),
+            MenuAction::Quit => exit.send(AppExit),
+        }
+    }
+}
+
+fn label(
+    action: MenuAction,
+    campaign: &Campaign,
+    stages: &Assets<Stage>,
+    options: &Options,
+    daily: &DailyLog,
+) -> String {
+    match action {
+        MenuAction::StartCampaign => "Start Campaign".to_string(),
+        MenuAction::Endless => "Endless".to_string(),
+        MenuAction::DailyChallenge => {
+            let played = DailyChallenge::today()
+                .is_some_and(|challenge| daily.entry(&challenge.date()).is_some());
+            if played {
+                "Daily Challenge (Practice)".to_string()
+            } else {
+                "Daily Challenge".to_string()
+            }
+        }
+        MenuAction::StageSelect => "Stage Select".to_string(),
+        MenuAction::Modifiers => {
+            let percent = options.modifiers.score_percent();
+            format!("Modifiers: x{}.{:0>2}", percent / 100, percent % 100)
+        }
+        MenuAction::Demo => "Demo".to_string(),
+        MenuAction::HighScores => "High Scores".to_string(),
+        MenuAction::Options => "Options".to_string(),
+        MenuAction::Quit => "Quit".to_string(),
+        MenuAction::Stage(i) => campaign
+            .stages
+            .get(i)
+            .and_then(|stage| stages.get(stage))
+            .map_or(format!("Stage {}", i + 1), |stage| stage.name.clone()),
+        MenuAction::ToggleFullscreen => {
+            format!(
+                "Fullscreen: {}",
+                if options.fullscreen { "On" } else { "Off" }
+            )
+        }
+        MenuAction::ToggleModifier(modifier) => {
+            let percent = modifier.score_percent();
+            format!(
+                "{}: {} (x{}.{:0>2})",
+                modifier.name(),
+                if options.modifiers.is_active(modifier) {
+                    "On"
+                } else {
+                    "Off"
+                },
+                percent / 100,
+                percent % 100
+            )
+        }
+        MenuAction::Back => "Back".to_string(),
+    }
+}
+
+/**
+ * Colors the menu with the palette of the first stage, or of the stage
+ * highlighted on the stage select
+ */
+pub fn menu_theme(
+    campaign: Res<Campaign>,
+    stages: Res<Assets<Stage>>,
+    options: Res<Options>,
+    daily: Res<DailyLog>,
+    page: Res<MenuPage>,
+    selection: Res<MenuSelection>,
+    items: Query<(&MenuItem, &Children)>,
+    mut texts: Query<&mut Text>,
+    title: Query<Entity, With<MenuTitle>>,
+    mut cameras: Query<&mut Camera2d>,
+) {
+    let shown = match *page {
+        MenuPage::Stages => selection.0,
+        _ => 0,
+    };
+    let stage = campaign
+        .stages
+        .get(shown)
+        .or(campaign.stages.first())
+        .and_then(|stage| stages.get(stage));
+    let (text_color, selected_color, background) =
+        stage.map_or((LIGHT, Color::WHITE, DARK), |stage| {
+            (
+                color_from_vec(&stage.text_cor),
+                color_from_vec(&stage.fg_cor),
+                color_from_vec(&stage.bg_cor),
+            )
+        });
+
+    for (item, children) in items.iter() {
+        let Some(mut text) = children
+            .first()
+            .and_then(|child| texts.get_mut(*child).ok())
+        else {
+            continue;
+        };
+        let selected = item.1 == selection.0;
+        let label = label(item.0, &campaign, &stages, &options, &daily);
+        text.sections[0].value = if selected {
+            format!("> {label} <")
+        } else {
+            label
+        };
+        text.sections[0].style.color = if selected { selected_color } else { text_color };
+    }
+    for entity in title.iter() {
+        if let Ok(mut text) = texts.get_mut(entity) {
+            text.sections[0].style.color = text_color;
+        }
+    }
+    for mut camera in cameras.iter_mut() {
+        camera.clear_color = ClearColorConfig::Custom(background);
+    }
+}
+
+pub fn apply_options(options: Res<Options>, mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    for mut window in windows.iter_mut() {
+        window.mode = if options.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+    }
 }
 
-pub fn teardown(mut commands: Commands, texts: Query<(Entity, With<MainMenuText>)>) {
-    for (entity, _) in texts.iter() {
-        commands.entity(entity).despawn();
+pub fn teardown(
+    mut commands: Commands,
+    texts: Query<Entity, With<MainMenuText>>,
+    mut windows: Query<&mut Window>,
+) {
+    for entity in texts.iter() {
+        commands.entity(entity).despawn_recursive();
     }
+    set_cursor_visible(&mut windows, false);
 }
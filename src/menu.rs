@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+
+// Whether a button was just pressed on any of the connected gamepads
+pub fn pad_pressed(
+    gamepads: &Gamepads,
+    pad_buttons: &Input<GamepadButton>,
+    kind: GamepadButtonType,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|pad| pad_buttons.just_pressed(GamepadButton::new(pad, kind)))
+}
+
+// The menus are pointed at with the mouse, in game the sight takes its place
+pub fn set_cursor_visible(windows: &mut Query<&mut Window>, visible: bool) {
+    for mut window in windows.iter_mut() {
+        window.cursor.visible = visible;
+    }
+}
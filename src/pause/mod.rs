@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+
+mod systems;
+use systems::*;
+
+use crate::{autopilot::Demo, game::prelude::PauseState, GameState};
+
+// Pauses the game on Escape, P or the Start button of a gamepad and when the
+// window loses focus, with a menu to resume, restart or quit the run
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PauseActionEvent>()
+            .init_resource::<PauseMenu>()
+            .add_systems(
+                Update,
+                (pause_keys, pause_on_focus_lost).run_if(
+                    in_state(GameState::InGame)
+                        .and_then(in_state(PauseState::Running))
+                        // any key ends the demo instead
+                        .and_then(not(resource_exists::<Demo>())),
+                ),
+            )
+            .add_systems(
+                Update,
+                (pause_menu_keys, pause_ui, pause_actions)
+                    .chain()
+                    .run_if(in_state(GameState::InGame).and_then(in_state(PauseState::Paused))),
+            )
+            .add_systems(OnEnter(PauseState::Paused), pause)
+            .add_systems(OnExit(PauseState::Paused), unpause)
+            // a run left some other way doesn't stay paused
+            .add_systems(OnExit(GameState::InGame), leave_pause);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PauseAction {
+    Resume,
+    RestartStage,
+    Options,
+    QuitToMenu,
+    ToggleFullscreen,
+    Back,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum PausePage {
+    #[default]
+    Main,
+    Options,
+}
+
+impl PausePage {
+    pub fn actions(self) -> &'static [PauseAction] {
+        match self {
+            PausePage::Main => &[
+                PauseAction::Resume,
+                PauseAction::RestartStage,
+                PauseAction::Options,
+                PauseAction::QuitToMenu,
+            ],
+            PausePage::Options => &[PauseAction::ToggleFullscreen, PauseAction::Back],
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    pub page: PausePage,
+    pub selection: usize,
+}
+
+#[derive(Event)]
+pub struct PauseActionEvent(pub PauseAction);
@@ -0,0 +1,202 @@
+use bevy::{prelude::*, window::WindowFocused};
+use bevy_egui::{
+    egui::{self, Align2, Color32, FontId, RichText, Sense},
+    EguiContexts,
+};
+
+use crate::{
+    game::prelude::{reset_run, PauseState},
+    main_menu::Options,
+    menu::{pad_pressed, set_cursor_visible},
+    replay::{Recording, Replay},
+    GameState,
+};
+
+use super::{PauseAction, PauseActionEvent, PauseMenu, PausePage};
+
+fn pause_pressed(
+    keyboard: &Input<KeyCode>,
+    gamepads: &Gamepads,
+    pad_buttons: &Input<GamepadButton>,
+) -> bool {
+    keyboard.any_just_pressed([KeyCode::Escape, KeyCode::P])
+        || pad_pressed(gamepads, pad_buttons, GamepadButtonType::Start)
+}
+
+pub fn pause_keys(
+    mut next_pause: ResMut<NextState<PauseState>>,
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+) {
+    if pause_pressed(&keyboard, &gamepads, &pad_buttons) {
+        next_pause.set(PauseState::Paused);
+    }
+}
+
+pub fn pause_on_focus_lost(
+    mut next_pause: ResMut<NextState<PauseState>>,
+    mut focus: EventReader<WindowFocused>,
+) {
+    if focus.iter().any(|event| !event.focused) {
+        next_pause.set(PauseState::Paused);
+    }
+}
+
+// Every timer of the game runs on `Time`, stopping it stops them all
+pub fn pause(mut time: ResMut<Time>, mut menu: ResMut<PauseMenu>, mut windows: Query<&mut Window>) {
+    time.pause();
+    *menu = PauseMenu::default();
+    set_cursor_visible(&mut windows, true);
+}
+
+pub fn unpause(mut time: ResMut<Time>) {
+    time.unpause();
+}
+
+pub fn leave_pause(mut next_pause: ResMut<NextState<PauseState>>, mut time: ResMut<Time>) {
+    next_pause.set(PauseState::Running);
+    time.unpause();
+}
+
+pub fn pause_menu_keys(
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    mut menu: ResMut<PauseMenu>,
+    mut actions: EventWriter<PauseActionEvent>,
+) {
+    let pad = |kind| pad_pressed(&gamepads, &pad_buttons, kind);
+    let page_actions = menu.page.actions();
+    let count = page_actions.len();
+
+    if keyboard.any_just_pressed([KeyCode::Up, KeyCode::W]) || pad(GamepadButtonType::DPadUp) {
+        menu.selection = (menu.selection + count - 1) % count;
+    }
+    if keyboard.any_just_pressed([KeyCode::Down, KeyCode::S]) || pad(GamepadButtonType::DPadDown) {
+        menu.selection = (menu.selection + 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::Return) || pad(GamepadButtonType::South) {
+        actions.send(PauseActionEvent(page_actions[menu.selection]));
+    }
+    // the pause key and back resume from the first page
+    if pause_pressed(&keyboard, &gamepads, &pad_buttons) || pad(GamepadButtonType::East) {
+        actions.send(PauseActionEvent(match menu.page {
+            PausePage::Main => PauseAction::Resume,
+            PausePage::Options => PauseAction::Back,
+        }));
+    }
+}
+
+fn label(action: PauseAction, options: &Options) -> String {
+    match action {
+        PauseAction::Resume => "Resume".to_string(),
+        PauseAction::RestartStage => "Restart Stage".to_string(),
+        PauseAction::Options => "Options".to_string(),
+        PauseAction::QuitToMenu => "Quit to Menu".to_string(),
+        PauseAction::ToggleFullscreen => {
+            format!(
+                "Fullscreen: {}",
+                if options.fullscreen { "On" } else { "Off" }
+            )
+        }
+        PauseAction::Back => "Back".to_string(),
+    }
+}
+
+pub fn pause_ui(
+    mut contexts: EguiContexts,
+    mut menu: ResMut<PauseMenu>,
+    options: Res<Options>,
+    mut actions: EventWriter<PauseActionEvent>,
+) {
+    egui::Area::new("pause")
+        .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., 0.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new("PAUSED")
+                        .font(FontId::proportional(24.))
+                        .color(Color32::WHITE),
+                );
+                for (i, action) in menu.page.actions().iter().enumerate() {
+                    let selected = i == menu.selection;
+                    let text = if selected {
+                        format!("> {} <", label(*action, &options))
+                    } else {
+                        label(*action, &options)
+                    };
+                    let response = ui.add(
+                        egui::Label::new(
+                            RichText::new(text).font(FontId::proportional(18.)).color(
+                                if selected {
+                                    Color32::YELLOW
+                                } else {
+                                    Color32::WHITE
+                                },
+                            ),
+                        )
+                        .sense(Sense::click()),
+                    );
+                    if response.hovered() {
+                        menu.selection = i;
+                    }
+                    if response.clicked() {
+                        actions.send(PauseActionEvent(*action));
+                    }
+                }
+            })
+        });
+}
+
+pub fn pause_actions(
+    mut commands: Commands,
+    mut actions: EventReader<PauseActionEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause: ResMut<NextState<PauseState>>,
+    mut menu: ResMut<PauseMenu>,
+    mut options: ResMut<Options>,
+    mut windows: Query<&mut Window>,
+    recording: Option<Res<Recording>>,
+    mut replay: Option<ResMut<Replay>>,
+) {
+    // a recording covers the run left here, a replay starts over with the next one
+    let mut end_run = |commands: &mut Commands| {
+        if let Some(recording) = recording.as_ref() {
+            recording.save();
+            commands.remove_resource::<Recording>();
+        }
+        if let Some(replay) = replay.as_mut() {
+            replay.next = 0;
+        }
+        reset_run(commands);
+    };
+
+    for PauseActionEvent(action) in actions.iter() {
+        match action {
+            PauseAction::Resume => {
+                next_pause.set(PauseState::Running);
+                set_cursor_visible(&mut windows, false);
+            }
+            // like a retry on the game over screen
+            PauseAction::RestartStage => {
+                end_run(&mut commands);
+                next_state.set(GameState::EnterGame);
+                set_cursor_visible(&mut windows, false);
+            }
+            PauseAction::QuitToMenu => {
+                end_run(&mut commands);
+                next_state.set(GameState::MainMenu);
+            }
+            PauseAction::Options => {
+                menu.page = PausePage::Options;
+                menu.selection = 0;
+            }
+            PauseAction::Back => {
+                menu.page = PausePage::Main;
+                menu.selection = 0;
+            }
+            PauseAction::ToggleFullscreen => options.fullscreen = !options.fullscreen,
+        }
+    }
+}
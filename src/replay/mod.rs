@@ -0,0 +1,166 @@
+use std::{fmt::Write, fs, str::FromStr};
+
+use bevy::prelude::*;
+
+mod systems;
+use systems::*;
+
+use crate::{
+    enter_stage::SkipStageIntro,
+    game::prelude::{FixedSeed, GameTick, InputSet, InputSource, PlayerInput, TickSet},
+    GameState,
+};
+
+const HEADER: &str = "invasion-replay 1";
+
+// Records the player input of a run to a file, or feeds a recorded run back
+// into the game instead of the mouse and keyboard
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        // an input a tick, so a run plays out the same both times
+        app.add_systems(
+            GameTick,
+            (
+                record_input
+                    .in_set(TickSet::Play)
+                    .run_if(in_state(GameState::InGame).and_then(resource_exists::<Recording>())),
+                play_back_input
+                    .in_set(InputSet)
+                    .run_if(in_state(GameState::InGame).and_then(resource_exists::<Replay>())),
+            ),
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            save_recording.run_if(resource_exists::<Recording>()),
+        )
+        .add_systems(
+            Last,
+            save_recording_on_exit.run_if(resource_exists::<Recording>()),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct Recording {
+    pub path: String,
+    pub seed: u64,
+    pub ticks: Vec<PlayerInput>,
+}
+
+impl Recording {
+    pub fn new(path: String, seed: u64) -> Self {
+        Self {
+            path,
+            seed,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn save(&self) {
+        match fs::write(&self.path, encode(self.seed, &self.ticks)) {
+            Ok(()) => info!("Saved {} ticks of input to {}", self.ticks.len(), self.path),
+            Err(err) => error!("Could not save the replay to {}: {err}", self.path),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Replay {
+    pub seed: u64,
+    pub ticks: Vec<PlayerInput>,
+    pub next: usize,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let text =
+            fs::read_to_string(path).map_err(|err| format!("could not read {path}: {err}"))?;
+        let (seed, ticks) = decode(&text)?;
+
+        Ok(Replay {
+            seed,
+            ticks,
+            next: 0,
+        })
+    }
+
+    // the recorded run is over, what is played next is played and seeded anew
+    pub fn end(commands: &mut Commands) {
+        commands.remove_resource::<Replay>();
+        commands.remove_resource::<SkipStageIntro>();
+        commands.remove_resource::<FixedSeed>();
+        commands.insert_resource(InputSource::Devices);
+    }
+}
+
+/**
+ * One line per run of identical ticks: `<ticks> <cursor x> <cursor y> <buttons>`
+ * where the buttons are 1 for fire, 2 for left and 4 for right, and the
+ * bits above them the number of the battery fired from (0 for the closest).
+ */
+pub fn encode(seed: u64, ticks: &[PlayerInput]) -> String {
+    let mut text = format!("{HEADER}\nseed {seed}\n");
+    let mut i = 0;
+    while i < ticks.len() {
+        let tick = ticks[i];
+        let repeat = ticks[i..]
+            .iter()
+            .take_while(|other| **other == tick)
+            .count();
+        let battery = tick.battery.map_or(0, |index| index + 1);
+        let buttons =
+            tick.fire as u8 | (tick.left as u8) << 1 | (tick.right as u8) << 2 | battery << 3;
+        // floats are written with just enough digits to read back the exact same value
+        let _ = writeln!(
+            text,
+            "{repeat} {} {} {buttons}",
+            tick.cursor.x, tick.cursor.y
+        );
+        i += repeat;
+    }
+
+    text
+}
+
+pub fn decode(text: &str) -> Result<(u64, Vec<PlayerInput>), String> {
+    let mut lines = text.lines();
+    if lines.next() != Some(HEADER) {
+        return Err("not an invasion replay".to_string());
+    }
+    let seed = lines
+        .next()
+        .and_then(|line| line.strip_prefix("seed "))
+        .and_then(|seed| seed.parse().ok())
+        .ok_or("the replay has no seed")?;
+
+    let mut ticks = Vec::new();
+    for (n, line) in lines
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+    {
+        let line_number = n + 3;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let &[repeat, x, y, buttons] = fields.as_slice() else {
+            return Err(format!("line {line_number}: expected 4 values"));
+        };
+        let repeat: usize = field(repeat, line_number)?;
+        let buttons: u8 = field(buttons, line_number)?;
+        let tick = PlayerInput {
+            cursor: Vec2::new(field(x, line_number)?, field(y, line_number)?),
+            fire: buttons & 1 != 0,
+            battery: (buttons >> 3).checked_sub(1),
+            left: buttons & 2 != 0,
+            right: buttons & 4 != 0,
+        };
+        ticks.extend(std::iter::repeat(tick).take(repeat));
+    }
+
+    Ok((seed, ticks))
+}
+
+fn field<T: FromStr>(value: &str, line_number: usize) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("line {line_number}: '{value}' is not a number"))
+}
@@ -0,0 +1,31 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::game::prelude::PlayerInput;
+
+use super::{Recording, Replay};
+
+pub fn record_input(input: Res<PlayerInput>, mut recording: ResMut<Recording>) {
+    recording.ticks.push(*input);
+}
+
+pub fn play_back_input(mut input: ResMut<PlayerInput>, mut replay: ResMut<Replay>) {
+    // the sight stays put once the recording runs out
+    let idle = PlayerInput {
+        cursor: input.cursor,
+        ..default()
+    };
+    *input = replay.ticks.get(replay.next).copied().unwrap_or(idle);
+    replay.next += 1;
+}
+
+// A recording covers a single run, it ends when the run does
+pub fn save_recording(mut commands: Commands, recording: Res<Recording>) {
+    recording.save();
+    commands.remove_resource::<Recording>();
+}
+
+pub fn save_recording_on_exit(exits: EventReader<AppExit>, recording: Res<Recording>) {
+    if !exits.is_empty() {
+        recording.save();
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+mod systems;
+use systems::*;
+
+use crate::{
+    enter_stage::SkipStageIntro,
+    game::prelude::{InputSource, UpgradeList},
+    replay::Recording,
+    GameState,
+};
+
+const UPGRADES_FILE: &str = "shop.upgrades.json";
+
+// Sells the upgrades of the upgrades file for score between the stages of
+// the campaign
+pub struct ShopPlugin;
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShopActionEvent>()
+            .init_resource::<ShopMenu>()
+            .add_systems(Startup, load_upgrades)
+            .add_systems(OnEnter(GameState::Shop), open_shop)
+            .add_systems(
+                Update,
+                (shop_keys, shop_ui, shop_actions)
+                    .chain()
+                    .run_if(in_state(GameState::Shop)),
+            )
+            .add_systems(OnExit(GameState::Shop), close_shop);
+    }
+}
+
+#[derive(Resource)]
+pub struct Upgrades(pub Handle<UpgradeList>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShopAction {
+    // buys the next level of the upgrade at this place in the list
+    Buy(usize),
+    Continue,
+}
+
+#[derive(Resource, Default)]
+pub struct ShopMenu {
+    pub selection: usize,
+}
+
+#[derive(Event)]
+pub struct ShopActionEvent(pub ShopAction);
+
+/**
+ * Whether the shop opens between stages. Runs that are recorded, replayed or
+ * played by the autopilot go straight on, their inputs don't cover the shop.
+ */
+#[derive(SystemParam)]
+pub struct ShopOpen<'w> {
+    input: Res<'w, InputSource>,
+    skip_intro: Option<Res<'w, SkipStageIntro>>,
+    recording: Option<Res<'w, Recording>>,
+}
+
+impl<'w> ShopOpen<'w> {
+    pub fn is_open(&self) -> bool {
+        *self.input == InputSource::Devices && self.skip_intro.is_none() && self.recording.is_none()
+    }
+}
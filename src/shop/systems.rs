@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32, FontId, RichText, Sense},
+    EguiContexts,
+};
+
+use crate::{
+    game::prelude::{BonusTally, RunUpgrades, Score, UpgradeList},
+    menu::{pad_pressed, set_cursor_visible},
+    GameState,
+};
+
+use super::{ShopAction, ShopActionEvent, ShopMenu, Upgrades, UPGRADES_FILE};
+
+pub fn load_upgrades(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Upgrades(asset_server.load(UPGRADES_FILE)));
+}
+
+// the bonus of the last wave can be spent right away
+pub fn open_shop(
+    mut menu: ResMut<ShopMenu>,
+    tally: Option<ResMut<BonusTally>>,
+    mut score: ResMut<Score>,
+    mut windows: Query<&mut Window>,
+) {
+    *menu = ShopMenu::default();
+    if let Some(mut tally) = tally {
+        score.0 += tally.finish();
+    }
+    set_cursor_visible(&mut windows, true);
+}
+
+pub fn close_shop(mut windows: Query<&mut Window>) {
+    set_cursor_visible(&mut windows, false);
+}
+
+// every upgrade of the list, then continue
+fn actions(upgrades: Option<&UpgradeList>) -> Vec<ShopAction> {
+    let count = upgrades.map_or(0, |upgrades| upgrades.0.len());
+    (0..count)
+        .map(ShopAction::Buy)
+        .chain([ShopAction::Continue])
+        .collect()
+}
+
+pub fn shop_keys(
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    mut menu: ResMut<ShopMenu>,
+    upgrades: Res<Upgrades>,
+    lists: Res<Assets<UpgradeList>>,
+    mut events: EventWriter<ShopActionEvent>,
+) {
+    let pad = |kind| pad_pressed(&gamepads, &pad_buttons, kind);
+    let actions = actions(lists.get(&upgrades.0));
+    let count = actions.len();
+
+    if keyboard.any_just_pressed([KeyCode::Up, KeyCode::W]) || pad(GamepadButtonType::DPadUp) {
+        menu.selection = (menu.selection + count - 1) % count;
+    }
+    if keyboard.any_just_pressed([KeyCode::Down, KeyCode::S]) || pad(GamepadButtonType::DPadDown) {
+        menu.selection = (menu.selection + 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::Return) || pad(GamepadButtonType::South) {
+        events.send(ShopActionEvent(actions[menu.selection.min(count - 1)]));
+    }
+    if keyboard.just_pressed(KeyCode::Space) || pad(GamepadButtonType::Start) {
+        events.send(ShopActionEvent(ShopAction::Continue));
+    }
+}
+
+pub fn shop_ui(
+    mut contexts: EguiContexts,
+    mut menu: ResMut<ShopMenu>,
+    upgrades: Res<Upgrades>,
+    lists: Res<Assets<UpgradeList>>,
+    bought: Res<RunUpgrades>,
+    score: Res<Score>,
+    mut events: EventWriter<ShopActionEvent>,
+) {
+    let list = lists.get(&upgrades.0);
+    let credits = bought.credits(score.0);
+
+    egui::Area::new("shop")
+        .anchor(Align2::CENTER_CENTER, egui::emath::vec2(0., 0.))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new("UPGRADES")
+                        .font(FontId::proportional(24.))
+                        .color(Color32::WHITE),
+                );
+                ui.label(
+                    RichText::new(format!("Credits: {credits}"))
+                        .font(FontId::proportional(18.))
+                        .color(Color32::YELLOW),
+                );
+                for (i, action) in actions(list).into_iter().enumerate() {
+                    let selected = i == menu.selection;
+                    let (text, color) = match (action, list) {
+                        (ShopAction::Buy(index), Some(list)) => {
+                            let upgrade = &list.0[index];
+                            let level = bought.level(index);
+                            match bought.price(index, upgrade) {
+                                Some(price) => (
+                                    format!(
+                                        "{} {}/{} - {price}",
+                                        upgrade.name, level, upgrade.max_level
+                                    ),
+                                    if price <= credits {
+                                        Color32::WHITE
+                                    } else {
+                                        Color32::GRAY
+                                    },
+                                ),
+                                None => {
+                                    (format!("{} - sold out", upgrade.name), Color32::DARK_GRAY)
+                                }
+                            }
+                        }
+                        _ => ("Continue".to_string(), Color32::WHITE),
+                    };
+                    let text = if selected {
+                        format!("> {text} <")
+                    } else {
+                        text
+                    };
+                    let response = ui.add(
+                        egui::Label::new(
+                            RichText::new(text)
+                                .font(FontId::proportional(18.))
+                                .color(if selected { Color32::YELLOW } else { color }),
+                        )
+                        .sense(Sense::click()),
+                    );
+                    if response.hovered() {
+                        menu.selection = i;
+                    }
+                    if response.clicked() {
+                        events.send(ShopActionEvent(action));
+                    }
+                }
+
+                let description = match (actions(list).get(menu.selection), list) {
+                    (Some(ShopAction::Buy(index)), Some(list)) => {
+                        list.0[*index].description.clone()
+                    }
+                    _ => String::new(),
+                };
+                ui.label(
+                    RichText::new(description)
+                        .font(FontId::proportional(12.))
+                        .color(Color32::WHITE),
+                );
+            })
+        });
+}
+
+pub fn shop_actions(
+    mut events: EventReader<ShopActionEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+    upgrades: Res<Upgrades>,
+    lists: Res<Assets<UpgradeList>>,
+    mut bought: ResMut<RunUpgrades>,
+    score: Res<Score>,
+) {
+    for ShopActionEvent(action) in events.iter() {
+        match action {
+            ShopAction::Buy(index) => {
+                if let Some(upgrade) = lists.get(&upgrades.0).and_then(|list| list.0.get(*index)) {
+                    bought.buy(*index, upgrade, score.0);
+                }
+            }
+            ShopAction::Continue => next_state.set(GameState::EnterGame),
+        }
+    }
+}